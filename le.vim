@@ -7,7 +7,8 @@ if exists("b:current_syntax")
   finish
 endif
 
-syn keyword leuchtkraftKeyword and forall
+syn keyword leuchtkraftKeyword and forall import module use
+syn region  leuchtkraftString start=+"+ end=+"+
 syn keyword leuchtkraftKeyword "=>"
 syn keyword leuchtkraftBoolean true false
 syn keyword leuchtkraftQuestion "?"
@@ -19,5 +20,6 @@ let b:current_syntax = "leuchtkraft"
 hi def link leuchtkraftKeyword       Keyword 
 hi def link leuchtkraftBoolean       Boolean
 hi def link leuchtkraftCommentLine   Comment
+hi def link leuchtkraftString        String
 hi def link leuchtkraftQuestion      Comment
 hi def link leuchtkraftFunction      Function
@@ -1,20 +1,96 @@
+use crate::debug::lint::{is_lint_name, Level, LintLevels};
+use crate::diagnostics::MessageFormat;
+use crate::exit::ExitStatus;
+use crate::logic::Limits;
+use crate::util::closest_match;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
-const HELP: &'static str = "
+const HELP: &str = "
 USAGE:
-    leuchtkraft [FLAGS] [file-name]
+    leuchtkraft [run] [FLAGS] [OPTIONS] [--] [file-name]...
+    leuchtkraft repl [FLAGS] [OPTIONS] [--] [file-name]...
+    leuchtkraft test <path>...
+    leuchtkraft fmt [--check] <path>...
+    leuchtkraft fix <path>...
+    leuchtkraft check <path>...
+    leuchtkraft lsp
+    leuchtkraft --explain <code>
+
+Options can also be given as '--option=value'. Arguments after '--' are never
+treated as flags or subcommands, for files with names like 'test' or '-x.le'.
 
 FLAGS:
+    --check             (fmt) Only check whether the scripts are formatted
     -h, --help          Prints help information
     -i, --interactive   Enter a Repl once the program exits
+    -j, --joint         List consistent combinations of values for all unknowns in a question
+    -m, --materialize   Print every fact that can be derived from the program
     -nc, --no-color     Disable colored diagnostics
-    -v, --version       Prints version information
+    --values            Print only the values of the unknowns in answers to --query, like 'true either'
+    -V, --version       Prints version information
+
+OPTIONS:
+    -A, --allow <lint>  Don't report a warning ('warnings' for all of them)
+    -W, --warn <lint>   Report a warning without failing
+    -D, --deny <lint>   Report a warning as an error
+    --explain <code>    Explain a diagnostic code like E003 in detail
+    -q, --query <question>
+                        Answer a question once the scripts were executed, then exit
+                        (can be given several times)
+    --message-format <human|json>
+                        Print diagnostics as colored text (default) or as one JSON object per line
+    --max-depth <n>     Maximum grounding depth when resolving a question
+    --max-atoms <n>     Maximum number of atoms considered when resolving a question
+    --max-steps <n>     Maximum number of resolution steps per question
+    --timeout <secs>    Maximum time spent on a single question
 
 ARGS:
-    <file-name>         Path to an executable Leuchtkraft script
+    <file-name>...      Paths to executable Leuchtkraft scripts, executed one after another
+
+SUBCOMMANDS:
+    run                 Execute the given scripts (the default)
+    repl                Execute the given scripts, then enter a Repl (like --interactive)
+    test                Check the answers to questions against their '// expect: X = false'
+                        comments in the given scripts or directories
+    fmt                 Format the given scripts or directories in place
+    fix                 Apply the suggested fixes for errors and warnings in the given
+                        scripts or directories
+    check               Report errors and warnings in the given scripts or directories without
+                        answering any questions
+    lsp                 Start a language server communicating over stdin/stdout
 ";
 
+/// Every flag and option, for suggestions when an unknown flag is given
+const FLAGS: [&str; 25] = [
+    "--help",
+    "-h",
+    "--version",
+    "-V",
+    "--interactive",
+    "-i",
+    "--joint",
+    "-j",
+    "--materialize",
+    "-m",
+    "--no-color",
+    "-nc",
+    "--values",
+    "--check",
+    "--allow",
+    "--warn",
+    "--deny",
+    "--explain",
+    "--query",
+    "-q",
+    "--message-format",
+    "--max-depth",
+    "--max-atoms",
+    "--max-steps",
+    "--timeout",
+];
+
 fn info() -> String {
     format!(
         "Leuchtkraft version {}\nMaintained by {}",
@@ -23,51 +99,400 @@ fn info() -> String {
     )
 }
 
+#[derive(Default)]
+pub enum Command {
+    /// Execute a script and/or enter a repl
+    #[default]
+    Run,
+    /// Check the expected answers in the given scripts or directories
+    Test(Vec<PathBuf>),
+    /// Format the given scripts or directories
+    Fmt {
+        paths: Vec<PathBuf>,
+        /// Only report unformatted files instead of changing them
+        check: bool,
+    },
+    /// Apply suggested fixes to the given scripts or directories
+    Fix(Vec<PathBuf>),
+    /// Report errors and warnings in the given scripts or directories without answering questions
+    Check(Vec<PathBuf>),
+    /// Run a language server over stdio
+    Lsp,
+    /// Print the explanation for a diagnostic code
+    Explain(String),
+}
+
+#[derive(Default)]
 pub struct Options {
-    /// Path to a executable Leuchtkraft script
-    pub file_name: Option<PathBuf>,
+    pub command: Command,
+
+    /// Paths to executable Leuchtkraft scripts, in the order they are executed
+    pub file_names: Vec<PathBuf>,
 
     /// Enter a repl once the program exits
     pub interactive: bool,
 
+    /// Answer questions with multiple unknowns jointly
+    pub joint: bool,
+
+    /// Print all derivable facts once the program exits
+    pub materialize: bool,
+
+    /// Questions to answer once the scripts were executed
+    pub queries: Vec<String>,
+
+    /// Print only the values of the unknowns when answering queries
+    pub values_only: bool,
+
     /// Disable colored output
     pub no_color: bool,
-}
 
-impl Default for Options {
-    fn default() -> Self {
-        Self {
-            file_name: None,
-            interactive: false,
-            no_color: false,
-        }
-    }
+    /// How diagnostics are printed
+    pub message_format: MessageFormat,
+
+    /// Resource limits for resolving questions
+    pub limits: Limits,
+
+    /// Lint levels given on the command line
+    pub lint_levels: LintLevels,
 }
 
 impl Options {
-    pub fn from_args() -> Option<Self> {
+    /// Parse the command line arguments.
+    /// If the program should exit right away (after printing help or because the
+    /// arguments are invalid), the status to exit with is returned instead.
+    pub fn from_args() -> Result<Self, ExitStatus> {
+        // The first argument is the path to the executable
+        Self::parse(env::args().skip(1))
+    }
+
+    fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, ExitStatus> {
         let mut options = Options::default();
+        let mut args = Args::new(args);
+        // Subcommands can only be given before any other positional argument
+        let mut expect_subcommand = true;
+
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg {
+                Arg::Positional(arg) => {
+                    if expect_subcommand && !args.positional_only {
+                        expect_subcommand = false;
+                        if options.set_subcommand(&arg) {
+                            continue;
+                        }
+                    }
+                    options.add_path(arg)?;
+                    continue;
+                }
+                Arg::Flag(flag, value) => (flag, value),
+            };
 
-        // first argument is the path to the executable.
-        // docs say we shouldn't rely on this behaviour - how tf are
-        // we supposed to deal with arguments randomly appearing
-        // in any sane way????
-        for arg in env::args().skip(1) {
-            match arg.as_ref() {
+            match flag.as_str() {
                 "--help" | "-h" => {
+                    no_value(&flag, value)?;
                     println!("{}", info());
                     println!("{}", HELP);
-                    return None;
+                    return Err(ExitStatus::Success);
                 }
-                "--version" | "-V" => {
+                "--version" | "-V" | "-v" => {
+                    no_value(&flag, value)?;
                     println!("{}", info());
-                    return None;
+                    return Err(ExitStatus::Success);
+                }
+                "--interactive" | "-i" => options.interactive = no_value(&flag, value)?,
+                "--joint" | "-j" => options.joint = no_value(&flag, value)?,
+                "--materialize" | "-m" => options.materialize = no_value(&flag, value)?,
+                "--no-color" | "-nc" => options.no_color = no_value(&flag, value)?,
+                "--values" => options.values_only = no_value(&flag, value)?,
+                "--check" => {
+                    no_value(&flag, value)?;
+                    match &mut options.command {
+                        Command::Fmt { check, .. } => *check = true,
+                        _ => {
+                            eprintln!("--check can only be used with 'leuchtkraft fmt'");
+                            return Err(ExitStatus::Error);
+                        }
+                    }
+                }
+                "--query" | "-q" => options.queries.push(args.value(&flag, value)?),
+                "--max-depth" => {
+                    options.limits.max_depth = Some(parse_value(&flag, args.value(&flag, value)?)?)
+                }
+                "--max-atoms" => {
+                    options.limits.max_atoms = Some(parse_value(&flag, args.value(&flag, value)?)?)
+                }
+                "--max-steps" => {
+                    options.limits.max_steps = Some(parse_value(&flag, args.value(&flag, value)?)?)
+                }
+                "--timeout" => {
+                    options.limits.timeout = Some(parse_timeout(&flag, args.value(&flag, value)?)?)
+                }
+                "--allow" | "-A" | "--warn" | "-W" | "--deny" | "-D" => {
+                    let level = match flag.as_str() {
+                        "--allow" | "-A" => Level::Allow,
+                        "--warn" | "-W" => Level::Warn,
+                        _ => Level::Deny,
+                    };
+                    let lint = args.value(&flag, value)?;
+                    if !is_lint_name(&lint) {
+                        eprintln!("Unknown lint {:?}, see 'leuchtkraft --explain W006'", lint);
+                        return Err(ExitStatus::Error);
+                    }
+                    options.lint_levels.set(&lint, level);
+                }
+                "--explain" => options.command = Command::Explain(args.value(&flag, value)?),
+                "--message-format" => {
+                    options.message_format = parse_message_format(&args.value(&flag, value)?)?
+                }
+                _ => {
+                    eprint!("Unknown flag {:?}", flag);
+                    match closest_match(&flag, FLAGS) {
+                        Some(suggestion) => eprintln!(", did you mean {:?}?", suggestion),
+                        None => eprintln!(", see 'leuchtkraft --help'"),
+                    }
+                    return Err(ExitStatus::Error);
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Switch to the subcommand with the given name.
+    /// Returns `false` if there is no such subcommand.
+    fn set_subcommand(&mut self, name: &str) -> bool {
+        match name {
+            "run" => {}
+            "repl" => self.interactive = true,
+            "test" => self.command = Command::Test(vec![]),
+            "fmt" => {
+                self.command = Command::Fmt {
+                    paths: vec![],
+                    check: false,
                 }
-                "--interactive" | "-i" => options.interactive = true,
-                "--no-color" | "-nc" => options.no_color = true,
-                x => options.file_name = Some(PathBuf::from(x)),
             }
+            "fix" => self.command = Command::Fix(vec![]),
+            "check" => self.command = Command::Check(vec![]),
+            "lsp" => self.command = Command::Lsp,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Add a positional argument to the files or paths of the command
+    fn add_path(&mut self, arg: String) -> Result<(), ExitStatus> {
+        match &mut self.command {
+            Command::Run => self.file_names.push(PathBuf::from(arg)),
+            Command::Test(paths)
+            | Command::Fmt { paths, .. }
+            | Command::Fix(paths)
+            | Command::Check(paths) => paths.push(PathBuf::from(arg)),
+            Command::Lsp | Command::Explain(_) => {
+                eprintln!("Unexpected argument {:?}", arg);
+                return Err(ExitStatus::Error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single command line argument
+enum Arg {
+    /// A flag like `--joint` or `-A`, along with the value given after an `=` (if any)
+    Flag(String, Option<String>),
+    /// Anything else, like a subcommand or a path
+    Positional(String),
+}
+
+/// Splits the command line arguments into flags and positional arguments
+struct Args<I> {
+    args: I,
+    /// Whether `--` was given, after which every argument is positional
+    positional_only: bool,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    fn new(args: I) -> Self {
+        Self {
+            args,
+            positional_only: false,
+        }
+    }
+
+    fn next(&mut self) -> Option<Arg> {
+        let arg = self.args.next()?;
+        // A single dash usually stands for stdin, so it is a positional argument
+        if self.positional_only || arg == "-" || !arg.starts_with('-') {
+            return Some(Arg::Positional(arg));
+        }
+        if arg == "--" {
+            self.positional_only = true;
+            return self.next();
+        }
+        Some(match arg.split_once('=') {
+            Some((flag, value)) => Arg::Flag(flag.to_owned(), Some(value.to_owned())),
+            None => Arg::Flag(arg, None),
+        })
+    }
+
+    /// Get the value of an option, either given after an `=` or as the next argument
+    fn value(&mut self, flag: &str, value: Option<String>) -> Result<String, ExitStatus> {
+        match value.or_else(|| self.args.next()) {
+            Some(value) => Ok(value),
+            None => {
+                eprintln!("Missing value for {}", flag);
+                Err(ExitStatus::Error)
+            }
+        }
+    }
+}
+
+/// Make sure a flag was given without a value, returning `true` to enable it
+fn no_value(flag: &str, value: Option<String>) -> Result<bool, ExitStatus> {
+    match value {
+        Some(value) => {
+            eprintln!("{} does not take a value, but {:?} was given", flag, value);
+            Err(ExitStatus::Error)
+        }
+        None => Ok(true),
+    }
+}
+
+/// Parse the value following an option, printing an error if that fails
+fn parse_value<T: std::str::FromStr>(option: &str, value: String) -> Result<T, ExitStatus> {
+    value.parse().map_err(|_| {
+        eprintln!("Invalid value for {}: {:?}", option, value);
+        ExitStatus::Error
+    })
+}
+
+/// Parse a number of seconds, which can't be negative or infinite
+fn parse_timeout(option: &str, value: String) -> Result<Duration, ExitStatus> {
+    let secs: f64 = parse_value(option, value.clone())?;
+    Duration::try_from_secs_f64(secs).map_err(|_| {
+        eprintln!("Invalid value for {}: {:?}", option, value);
+        ExitStatus::Error
+    })
+}
+
+fn parse_message_format(value: &str) -> Result<MessageFormat, ExitStatus> {
+    match value {
+        "human" => Ok(MessageFormat::Human),
+        "json" => Ok(MessageFormat::Json),
+        other => {
+            eprintln!(
+                "Invalid value for --message-format: {:?} (expected human or json)",
+                other
+            );
+            Err(ExitStatus::Error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, ExitStatus> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn timeout() {
+        let options = parse(&["--timeout", "1.5"]).unwrap();
+        assert_eq!(options.limits.timeout, Some(Duration::from_millis(1500)));
+        let options = parse(&["--timeout=0"]).unwrap();
+        assert_eq!(options.limits.timeout, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn invalid_timeout() {
+        for secs in ["-1", "nan", "inf", "1e100", "soon"] {
+            let flag = format!("--timeout={}", secs);
+            assert_eq!(parse(&[&flag]).err(), Some(ExitStatus::Error), "{}", secs);
+        }
+    }
+
+    #[test]
+    fn files() {
+        let options = parse(&["a.le", "-", "b.le"]).unwrap();
+        assert!(matches!(options.command, Command::Run));
+        assert_eq!(options.file_names, ["a.le", "-", "b.le"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn subcommands() {
+        let options = parse(&["fmt", "--check", "src"]).unwrap();
+        assert!(
+            matches!(options.command, Command::Fmt { paths, check: true } if paths == [PathBuf::from("src")])
+        );
+
+        let options = parse(&["repl", "a.le"]).unwrap();
+        assert!(options.interactive);
+        assert_eq!(options.file_names, [PathBuf::from("a.le")]);
+
+        assert!(matches!(parse(&["lsp"]).unwrap().command, Command::Lsp));
+        assert_eq!(parse(&["lsp", "a.le"]).err(), Some(ExitStatus::Error));
+        // --check only makes sense when formatting
+        assert_eq!(parse(&["check", "--check"]).err(), Some(ExitStatus::Error));
+    }
+
+    #[test]
+    fn subcommands_come_first() {
+        // A file named like a subcommand after another path
+        let options = parse(&["a.le", "test"]).unwrap();
+        assert!(matches!(options.command, Command::Run));
+        assert_eq!(options.file_names, ["a.le", "test"].map(PathBuf::from));
+
+        let options = parse(&["test", "fmt"]).unwrap();
+        assert!(matches!(options.command, Command::Test(paths) if paths == [PathBuf::from("fmt")]));
+    }
+
+    #[test]
+    fn positional_only() {
+        let options = parse(&["-j", "--", "test", "-x.le", "--joint"]).unwrap();
+        assert!(options.joint);
+        assert!(matches!(options.command, Command::Run));
+        assert_eq!(
+            options.file_names,
+            ["test", "-x.le", "--joint"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn values() {
+        let options = parse(&[
+            "-q",
+            "X? => a(x) => X?",
+            "--query=Y? => b(y) => Y?",
+            "--max-depth=3",
+            "--max-atoms",
+            "100",
+            "--message-format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(options.queries, ["X? => a(x) => X?", "Y? => b(y) => Y?"]);
+        assert_eq!(options.limits.max_depth, Some(3));
+        assert_eq!(options.limits.max_atoms, Some(100));
+        assert!(matches!(options.message_format, MessageFormat::Json));
+
+        // Values may contain an '='
+        let options = parse(&["--query=X? => a(x=1) => X?"]).unwrap();
+        assert_eq!(options.queries, ["X? => a(x=1) => X?"]);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        for args in [
+            &["--jiont"][..],
+            &["--joint=yes"],
+            &["--help=me"],
+            &["--query"],
+            &["--max-depth", "deep"],
+            &["--message-format=xml"],
+            &["--allow", "not_a_lint"],
+        ] {
+            assert_eq!(parse(args).err(), Some(ExitStatus::Error), "{:?}", args);
         }
-        Some(options)
     }
 }
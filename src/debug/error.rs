@@ -1,50 +1,214 @@
-use crate::diagnostics::{Annotation, AnnotationType, Diagnostic};
+use crate::diagnostics::{Annotation, AnnotationType, Diagnostic, Location, Suggestion};
 use crate::parser::span::Span;
+use crate::parser::tokenizer::slice;
 
 pub enum Error {
     UnexpectedIndent,
-    FreedVarInQuestion { span: Span },
+    FreedVarInQuestion {
+        span: Span,
+        /// Where the variable was freed
+        declaration: Option<(Location, Span)>,
+    },
+    /// An imported file could not be read
+    ImportFailed {
+        span: Span,
+        path: String,
+        reason: String,
+    },
+    /// An imported file is still being executed
+    CyclicImport {
+        span: Span,
+        path: String,
+    },
+    /// A module that no file declared so far
+    UnknownModule {
+        span: Span,
+        module: String,
+    },
+    /// A qualified name whose module doesn't contain the predicate
+    UnknownPredicate {
+        span: Span,
+        module: String,
+        predicate: String,
+    },
+    /// An unqualified name that several modules in scope contain
+    AmbiguousName {
+        span: Span,
+        name: String,
+        /// The qualified names it could refer to
+        candidates: Vec<String>,
+    },
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnexpectedIndent => "E002",
+            Error::FreedVarInQuestion { .. } => "E003",
+            Error::ImportFailed { .. } => "E008",
+            Error::CyclicImport { .. } => "E009",
+            Error::UnknownModule { .. } => "E010",
+            Error::UnknownPredicate { .. } => "E011",
+            Error::AmbiguousName { .. } => "E012",
+        }
+    }
 }
 
 impl<'a> From<(Error, &'a str)> for Diagnostic<'a> {
     fn from(other: (Error, &'a str)) -> Self {
-        let annotations = match other.0 {
+        let annotations = match &other.0 {
             Error::UnexpectedIndent => vec![Annotation {
                 annotation_type: AnnotationType::Info,
                 span: Span::from(0), // indents are always at the beginning
                 msg: "expected no indentation".to_owned(),
+                location: None,
+            }],
+            Error::FreedVarInQuestion { span, declaration } => {
+                let span = *span;
+                let mut annotations = vec![Annotation {
+                    annotation_type: AnnotationType::Info,
+                    span,
+                    msg: format!("{:?} was previously freed", slice(other.1, span)),
+                    location: None,
+                }];
+                if let Some((location, span)) = declaration {
+                    annotations.push(Annotation {
+                        annotation_type: AnnotationType::Note,
+                        span: *span,
+                        msg: "freed here".to_owned(),
+                        location: Some(*location),
+                    });
+                }
+                annotations
+            }
+            Error::ImportFailed { span, reason, .. } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: reason.clone(),
+                location: None,
+            }],
+            Error::CyclicImport { span, .. } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: "this file is still being executed".to_owned(),
+                location: None,
+            }],
+            Error::UnknownModule { span, .. } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: "no file declared this module".to_owned(),
+                location: None,
+            }],
+            Error::UnknownPredicate {
+                span,
+                module,
+                predicate,
+            } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: format!("module {:?} has no predicate {:?}", module, predicate),
+                location: None,
             }],
-            Error::FreedVarInQuestion { span } => vec![Annotation {
+            Error::AmbiguousName {
+                span, candidates, ..
+            } => vec![Annotation {
                 annotation_type: AnnotationType::Info,
-                span: span,
-                msg: format!("{:?} was previously freed", &other.1[span.0..span.1]),
+                span: *span,
+                msg: format!("could refer to {}", candidates.join(" or ")),
+                location: None,
             }],
         };
 
-        let msg = match other.0 {
+        let msg = match &other.0 {
             Error::UnexpectedIndent => "Unexpected indentation level".to_owned(),
-            Error::FreedVarInQuestion { span } => format!(
+            Error::FreedVarInQuestion { span, .. } => format!(
                 "{:?} is a free variable and may not be used in a question",
-                &other.1[span.0..span.1]
+                slice(other.1, *span)
             ),
+            Error::ImportFailed { path, .. } => format!("Could not import {:?}", path),
+            Error::CyclicImport { path, .. } => format!("Cyclic import of {:?}", path),
+            Error::UnknownModule { module, .. } => format!("Unknown module {:?}", module),
+            Error::UnknownPredicate {
+                module, predicate, ..
+            } => format!("Unknown predicate \"{}.{}\"", module, predicate),
+            Error::AmbiguousName { name, .. } => format!("Ambiguous predicate {:?}", name),
         };
 
-        let note = match other.0 {
-            Error::UnexpectedIndent { .. } => {
+        let note = match &other.0 {
+            Error::UnexpectedIndent => {
                 Some("any number of spaces/tabs at the beginning of a line count as indentation")
             }
             Error::FreedVarInQuestion { .. } => Some(
                 "free variables in questions are intended to be implemented in a future release",
             ),
+            Error::ImportFailed { .. } => {
+                Some("imported paths are relative to the directory of the importing file")
+            }
+            Error::CyclicImport { .. } => {
+                Some("a file cannot import itself, neither directly nor through other files")
+            }
+            Error::UnknownModule { .. } => {
+                Some("modules are declared with 'module name' and have to be imported before they are used")
+            }
+            Error::UnknownPredicate { .. } => {
+                Some("predicates can only be added to a module from within the module itself")
+            }
+            Error::AmbiguousName { .. } => Some("use a qualified name like 'geo.inside' instead"),
+        };
+
+        let suggestions = match &other.0 {
+            Error::UnexpectedIndent => {
+                let indent = other.1.chars().take_while(|c| c.is_whitespace()).count();
+                vec![Suggestion {
+                    msg: "remove the indentation".to_owned(),
+                    span: Span(0, indent),
+                    replacement: String::new(),
+                    location: None,
+                }]
+            }
+            Error::FreedVarInQuestion { .. }
+            | Error::ImportFailed { .. }
+            | Error::CyclicImport { .. }
+            | Error::UnknownModule { .. }
+            | Error::UnknownPredicate { .. }
+            | Error::AmbiguousName { .. } => vec![],
         };
 
         Self {
-            code: None,
+            code: Some(other.0.code()),
             buffer: other.1,
             annotation_type: AnnotationType::Error,
-            annotations: annotations,
-            msg: msg,
-            note: note,
+            annotations,
+            msg,
+            note,
+            suggestions,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::repl::Context;
+
+    #[test]
+    fn freed_var_in_question() {
+        let mut i = Interpreter::new();
+        let source = i.add_source(Context::Repl);
+        let forall = i.push_line(source, "forall X");
+        assert!(i.execute("forall X", forall, &mut vec![]).is_ok());
+
+        // The variable comes after non-ASCII characters
+        let line = "    öö(X) => Y?";
+        let location = i.push_line(source, line);
+        let Err(diagnostic) = i.execute(line, location, &mut vec![]) else {
+            panic!("questions can't contain free variables");
+        };
+        assert_eq!(
+            diagnostic.msg,
+            "\"X\" is a free variable and may not be used in a question"
+        );
+        assert_eq!(diagnostic.annotations[0].msg, "\"X\" was previously freed");
+        assert_eq!(diagnostic.annotations[1].location, Some(forall));
+    }
+}
@@ -0,0 +1,42 @@
+//! Long-form explanations for diagnostic codes, shown by `leuchtkraft --explain <code>`.
+//!
+//! Codes are permanent: once assigned, a code is never reused for a different diagnostic.
+
+const EXPLANATIONS: [(&str, &str); 23] = [
+    ("E001", include_str!("explanations/E001.md")),
+    ("E002", include_str!("explanations/E002.md")),
+    ("E003", include_str!("explanations/E003.md")),
+    ("E004", include_str!("explanations/E004.md")),
+    ("E005", include_str!("explanations/E005.md")),
+    ("E006", include_str!("explanations/E006.md")),
+    ("E007", include_str!("explanations/E007.md")),
+    ("E008", include_str!("explanations/E008.md")),
+    ("E009", include_str!("explanations/E009.md")),
+    ("E010", include_str!("explanations/E010.md")),
+    ("E011", include_str!("explanations/E011.md")),
+    ("E012", include_str!("explanations/E012.md")),
+    ("W001", include_str!("explanations/W001.md")),
+    ("W002", include_str!("explanations/W002.md")),
+    ("W003", include_str!("explanations/W003.md")),
+    ("W004", include_str!("explanations/W004.md")),
+    ("W005", include_str!("explanations/W005.md")),
+    ("W006", include_str!("explanations/W006.md")),
+    ("W007", include_str!("explanations/W007.md")),
+    ("W008", include_str!("explanations/W008.md")),
+    ("W009", include_str!("explanations/W009.md")),
+    ("W010", include_str!("explanations/W010.md")),
+    ("W011", include_str!("explanations/W011.md")),
+];
+
+/// Look up the explanation for a code like `E003` (case-insensitive)
+pub fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+/// All codes that have an explanation
+pub fn codes() -> impl Iterator<Item = &'static str> {
+    EXPLANATIONS.iter().map(|(code, _)| *code)
+}
@@ -0,0 +1,388 @@
+//! Controls how severe each kind of warning is.
+//!
+//! Lint levels are combined from (lowest to highest priority):
+//! * the `[lints]` section of a `leuchtkraft.toml` file
+//! * command line flags like `-D warnings` or `-A purely_literal_clause`
+//! * pragmas on their own line (`// leuchtkraft: deny(redundant_true)`),
+//!   which apply to the rest of the file
+//! * pragmas after code, which only apply to that line
+
+use super::warning::{Warning, LINT_NAMES};
+use crate::diagnostics::{AnnotationType, Diagnostic};
+use crate::parser::span::{Span, Spanned};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name that refers to all lints at once
+const ALL_LINTS: &str = "warnings";
+
+/// The name of the project configuration file
+pub const CONFIG_FILE: &str = "leuchtkraft.toml";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    /// Don't report the warning at all
+    Allow,
+    /// Report the warning, but keep going
+    Warn,
+    /// Report the warning as an error
+    Deny,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a name refers to one or more lints
+pub fn is_lint_name(name: &str) -> bool {
+    name == ALL_LINTS || LINT_NAMES.contains(&name)
+}
+
+#[derive(Clone, Default)]
+pub struct LintLevels {
+    /// Later entries override earlier ones
+    levels: Vec<(String, Level)>,
+}
+
+impl LintLevels {
+    /// Set the level of a lint (or of all lints, for `warnings`).
+    /// The name is not checked, see [is_lint_name].
+    pub fn set(&mut self, lint: &str, level: Level) {
+        self.levels.push((lint.to_owned(), level));
+    }
+
+    /// Add all the levels from another set of levels, overriding the current ones
+    pub fn extend(&mut self, other: &LintLevels) {
+        self.levels.extend(other.levels.iter().cloned());
+    }
+
+    /// The level of a lint, if it was set explicitly
+    fn get(&self, lint: &str) -> Option<Level> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|(name, _)| name == lint || name == ALL_LINTS)
+            .map(|(_, level)| *level)
+    }
+
+    /// Parse the `[lints]` section of a config file
+    pub fn parse_config(config: &str) -> Result<Self, String> {
+        let mut levels = LintLevels::default();
+        let mut section = None;
+        for (ix, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let error = |msg: String| format!("line {}: {}", ix + 1, msg);
+
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if name.trim() != "lints" {
+                    return Err(error(format!("unknown section {:?}", name.trim())));
+                }
+                section = Some(name.trim());
+                continue;
+            }
+            if section.is_none() {
+                return Err(error("expected a section like [lints]".to_owned()));
+            }
+
+            let (lint, level) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected 'lint = \"level\"'".to_owned()))?;
+            let (lint, level) = (lint.trim(), level.trim().trim_matches('"'));
+            if !is_lint_name(lint) {
+                return Err(error(format!("unknown lint {:?}", lint)));
+            }
+            let level = Level::from_name(level).ok_or_else(|| {
+                error(format!(
+                    "invalid level {:?} (expected allow, warn or deny)",
+                    level
+                ))
+            })?;
+            levels.set(lint, level);
+        }
+        Ok(levels)
+    }
+
+    /// Find the config file closest to a directory (looking at its ancestors too)
+    /// and load the lint levels from it.
+    /// Errors contain the path of the config file and the reason it could not be loaded.
+    pub fn discover(dir: &Path) -> Result<Self, (PathBuf, String)> {
+        // Relative paths would stop at the working directory
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let config_path = match dir
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+        {
+            Some(path) => path,
+            None => return Ok(LintLevels::default()),
+        };
+        let config = fs::read_to_string(&config_path)
+            .map_err(|e| (config_path.clone(), format!("{:?}", e)))?;
+        Self::parse_config(&config).map_err(|msg| (config_path, msg))
+    }
+
+    /// The lint levels for a script: those from the config file closest to it,
+    /// overridden by the given ones (usually from the command line)
+    pub fn for_script(script: &Path, overrides: &LintLevels) -> Result<Self, (PathBuf, String)> {
+        let dir = script.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut levels = Self::discover(&dir)?;
+        levels.extend(overrides);
+        Ok(levels)
+    }
+}
+
+/// Keeps track of the lint levels while a program is executed line by line
+#[derive(Clone, Default)]
+pub struct Linter {
+    /// Levels from the config file, the command line and pragmas on their own line
+    levels: LintLevels,
+    /// Levels from a pragma on the current line
+    line_levels: LintLevels,
+}
+
+impl Linter {
+    pub fn new(levels: LintLevels) -> Self {
+        Self {
+            levels,
+            line_levels: LintLevels::default(),
+        }
+    }
+
+    fn level(&self, lint: &str) -> Level {
+        self.line_levels
+            .get(lint)
+            .or_else(|| self.levels.get(lint))
+            .unwrap_or(Level::Warn)
+    }
+
+    /// Start processing a new line, applying the lint pragma in its comment (if any).
+    pub fn enter_line(
+        &mut self,
+        has_code: bool,
+        comment: Option<&Spanned<&str>>,
+        warnings: &mut Vec<Warning>,
+    ) {
+        self.line_levels = LintLevels::default();
+        let comment = match comment {
+            Some(comment) => comment,
+            None => return,
+        };
+        let levels = match parse_pragma(comment, warnings) {
+            Some(levels) => levels,
+            None => return,
+        };
+        if has_code {
+            self.line_levels = levels;
+        } else {
+            self.levels.extend(&levels);
+        }
+    }
+
+    /// Create a diagnostic for a warning according to its lint level.
+    /// Returns `None` if the warning is allowed.
+    pub fn diagnostic<'a>(&self, warning: &'a Warning, line: &'a str) -> Option<Diagnostic<'a>> {
+        let mut diagnostic = Diagnostic::from((warning, line));
+        match self.level(warning.name()) {
+            Level::Allow => return None,
+            Level::Warn => {}
+            Level::Deny => diagnostic.annotation_type = AnnotationType::Error,
+        }
+        Some(diagnostic)
+    }
+}
+
+/// Parse a pragma like `leuchtkraft: allow(redundant_true) deny(redundant_false)`.
+/// Returns `None` if the comment is not a pragma.
+fn parse_pragma(comment: &Spanned<&str>, warnings: &mut Vec<Warning>) -> Option<LintLevels> {
+    let text = *comment.as_inner();
+    let offset = comment.span().0;
+    let start = text.len() - text.trim_start().len();
+    if !text[start..].starts_with("leuchtkraft:") {
+        return None;
+    }
+    let mut pos = start + "leuchtkraft:".len();
+
+    // The text is searched by byte, but spans are character positions
+    let span = |start: usize, end: usize| {
+        Span(
+            offset + text[..start].chars().count(),
+            offset + text[..end].chars().count(),
+        )
+    };
+    let malformed = Warning::MalformedPragma {
+        span: span(start, text.trim_end().len()),
+    };
+    let skip_whitespace = |pos: &mut usize| {
+        *pos += text[*pos..].len() - text[*pos..].trim_start().len();
+    };
+    let read_word = |pos: &mut usize| {
+        let word_start = *pos;
+        *pos += text[*pos..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(text.len() - *pos);
+        &text[word_start..*pos]
+    };
+
+    let mut levels = LintLevels::default();
+    skip_whitespace(&mut pos);
+    if pos == text.len() {
+        warnings.push(malformed);
+        return None;
+    }
+    while pos != text.len() {
+        let level = match Level::from_name(read_word(&mut pos)) {
+            Some(level) => level,
+            None => {
+                warnings.push(malformed);
+                return None;
+            }
+        };
+        skip_whitespace(&mut pos);
+        if !text[pos..].starts_with('(') {
+            warnings.push(malformed);
+            return None;
+        }
+        pos += 1;
+
+        loop {
+            skip_whitespace(&mut pos);
+            let name_start = pos;
+            let name = read_word(&mut pos);
+            if name.is_empty() {
+                warnings.push(malformed);
+                return None;
+            }
+            if is_lint_name(name) {
+                levels.set(name, level);
+            } else {
+                warnings.push(Warning::UnknownLint {
+                    name: name.to_owned(),
+                    span: span(name_start, pos),
+                });
+            }
+
+            skip_whitespace(&mut pos);
+            match text[pos..].chars().next() {
+                Some(',') => pos += 1,
+                Some(')') => {
+                    pos += 1;
+                    break;
+                }
+                _ => {
+                    warnings.push(malformed);
+                    return None;
+                }
+            }
+        }
+        skip_whitespace(&mut pos);
+    }
+    Some(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::Parser;
+
+    /// The warnings for the pragma in the comment of a line
+    fn pragma_warnings(line: &str) -> Vec<Warning> {
+        let mut warnings = vec![];
+        let statement = Parser::new(line).line(&mut warnings).ok().unwrap();
+        parse_pragma(statement.comment.as_ref().unwrap(), &mut warnings);
+        warnings
+    }
+
+    #[test]
+    fn config() {
+        let levels = LintLevels::parse_config(
+            "# comment\n[lints]\nredundant_true = \"deny\" # no\nwarnings = allow\n",
+        )
+        .unwrap();
+        assert_eq!(levels.get("redundant_true"), Some(Level::Allow));
+        assert_eq!(levels.get("redundant_false"), Some(Level::Allow));
+
+        let levels = LintLevels::parse_config("[lints]\nredundant_true = \"deny\"\n").unwrap();
+        assert_eq!(levels.get("redundant_true"), Some(Level::Deny));
+        assert_eq!(levels.get("redundant_false"), None);
+    }
+
+    #[test]
+    fn config_errors() {
+        let error = |config| LintLevels::parse_config(config).err().unwrap();
+        assert_eq!(
+            error("redundant_true = \"deny\""),
+            "line 1: expected a section like [lints]"
+        );
+        assert_eq!(error("[lint]"), "line 1: unknown section \"lint\"");
+        assert_eq!(
+            error("[lints]\nredundant_true"),
+            "line 2: expected 'lint = \"level\"'"
+        );
+        assert_eq!(
+            error("[lints]\n\nredundant = \"deny\""),
+            "line 3: unknown lint \"redundant\""
+        );
+        assert_eq!(
+            error("[lints]\nredundant_true = \"forbid\""),
+            "line 2: invalid level \"forbid\" (expected allow, warn or deny)"
+        );
+    }
+
+    #[test]
+    fn priority() {
+        let mut levels = LintLevels::parse_config("[lints]\nredundant_true = \"deny\"").unwrap();
+        let mut cli_levels = LintLevels::default();
+        cli_levels.set("warnings", Level::Allow);
+        levels.extend(&cli_levels);
+
+        // The command line overrides the config file
+        let mut linter = Linter::new(levels);
+        assert_eq!(linter.level("redundant_true"), Level::Allow);
+        assert_eq!(linter.level("redundant_false"), Level::Allow);
+
+        // A pragma on its own line overrides the command line for the rest of the file
+        let file_pragma = Spanned::new(" leuchtkraft: warn(redundant_true)", Span(2, 36));
+        linter.enter_line(false, Some(&file_pragma), &mut vec![]);
+        assert_eq!(linter.level("redundant_true"), Level::Warn);
+        linter.enter_line(true, None, &mut vec![]);
+        assert_eq!(linter.level("redundant_true"), Level::Warn);
+
+        // A pragma after code only applies to that line
+        let line_pragma = Spanned::new(" leuchtkraft: deny(redundant_true)", Span(15, 49));
+        linter.enter_line(true, Some(&line_pragma), &mut vec![]);
+        assert_eq!(linter.level("redundant_true"), Level::Deny);
+        linter.enter_line(true, None, &mut vec![]);
+        assert_eq!(linter.level("redundant_true"), Level::Warn);
+        assert_eq!(linter.level("redundant_false"), Level::Allow);
+    }
+
+    #[test]
+    fn pragma_spans_count_characters() {
+        let line = "true => a(x) // leuchtkraft: allow(größe_xyz)";
+        let warnings = pragma_warnings(line);
+        let [Warning::UnknownLint { name, span }] = &warnings[..] else {
+            panic!("expected a single unknown lint");
+        };
+        assert_eq!(name, "größe_xyz");
+        assert_eq!(span.1 - span.0, "größe_xyz".chars().count());
+        assert_eq!(span.1, line.chars().count() - 1);
+
+        let line = "true => ä(x) // leuchtkraft: allow(";
+        let warnings = pragma_warnings(line);
+        let [Warning::MalformedPragma { span }] = &warnings[..] else {
+            panic!("expected a malformed pragma");
+        };
+        assert_eq!(span.0, "true => ä(x) // ".chars().count());
+        assert_eq!(span.1, line.chars().count());
+    }
+}
@@ -1,5 +1,8 @@
 //! Debug information returned by the interpreter
 
 pub mod error;
+pub mod explain;
+pub mod lint;
 pub mod panic;
+pub mod semantic;
 pub mod warning;
@@ -0,0 +1,256 @@
+//! Lints that need to know about more than a single clause, like
+//! predicates that are used with different numbers of arguments.
+
+use super::warning::Warning;
+use crate::diagnostics::Location;
+use crate::parser::span::{Span, Spanned};
+use crate::parser::symbol::Atom;
+use crate::util::closest_match;
+use std::collections::HashMap;
+
+/// A forall statement whose block is still open
+struct ForallBlock {
+    /// The forall statement
+    location: Location,
+    /// The freed variables and whether or not they were used
+    vars: Vec<(String, Span, bool)>,
+    /// All constants used inside the block
+    constants: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct SemanticLinter {
+    /// The number of arguments every predicate was first used with,
+    /// and where it was first used
+    arities: HashMap<String, (usize, (Location, Span))>,
+    /// Predicates that appear in clauses that aren't questions
+    defined: Vec<String>,
+    block: Option<ForallBlock>,
+}
+
+impl SemanticLinter {
+    /// Open a new forall block, closing the previous one (if any)
+    pub fn enter_forall(
+        &mut self,
+        location: Location,
+        vars: &[Spanned<&str>],
+        warnings: &mut Vec<Warning>,
+    ) {
+        self.close_block(warnings);
+        self.block = Some(ForallBlock {
+            location,
+            vars: vars
+                .iter()
+                .map(|var| (var.as_inner().to_string(), var.span(), false))
+                .collect(),
+            constants: vec![],
+        });
+    }
+
+    /// Close the current forall block, warning about any variables that weren't used
+    pub fn close_block(&mut self, warnings: &mut Vec<Warning>) {
+        let block = match self.block.take() {
+            Some(block) => block,
+            None => return,
+        };
+        for (name, span, used) in &block.vars {
+            // Variables freed twice are already reported as duplicates
+            let is_duplicate = block.vars.iter().filter(|(n, ..)| n == name).count() > 1;
+            if !used && !is_duplicate {
+                warnings.push(Warning::UnusedForallVariable {
+                    name: name.clone(),
+                    span: *span,
+                    location: block.location,
+                    suggestion: closest_match(name, block.constants.iter().map(String::as_str))
+                        .map(str::to_owned),
+                });
+            }
+        }
+    }
+
+    /// Check a rule against everything that came before it
+    pub fn check_rule(
+        &mut self,
+        and_chains: &[Vec<Spanned<Atom>>],
+        is_question: bool,
+        location: Location,
+        warnings: &mut Vec<Warning>,
+    ) {
+        let predicates: Vec<(&str, &[Spanned<&str>], Span)> = and_chains
+            .iter()
+            .flatten()
+            .filter_map(|atom| match atom.as_inner() {
+                Atom::Predicate(name, args) => Some((*name, args.as_slice(), atom.span())),
+                _ => None,
+            })
+            .collect();
+
+        for (name, args, span) in &predicates {
+            match self.arities.get(*name) {
+                Some((expected, first_use)) if *expected != args.len() => {
+                    let suggestion = closest_match(
+                        name,
+                        self.arities
+                            .iter()
+                            .filter(|(_, (arity, _))| *arity == args.len())
+                            .map(|(other, _)| other.as_str()),
+                    );
+                    warnings.push(Warning::ArityMismatch {
+                        name: name.to_string(),
+                        arity: args.len(),
+                        expected: *expected,
+                        first_use: *first_use,
+                        span: *span,
+                        suggestion: suggestion.map(str::to_owned),
+                    });
+                    continue;
+                }
+                Some(_) => {}
+                None => {
+                    self.arities
+                        .insert(name.to_string(), (args.len(), (location, *span)));
+                }
+            }
+
+            if is_question && !self.defined.iter().any(|defined| defined == name) {
+                warnings.push(Warning::UndefinedPredicate {
+                    name: name.to_string(),
+                    span: *span,
+                    suggestion: closest_match(name, self.defined.iter().map(String::as_str))
+                        .map(str::to_owned),
+                });
+            }
+        }
+
+        if !is_question {
+            for (name, ..) in &predicates {
+                if !self.defined.iter().any(|defined| defined == name) {
+                    self.defined.push(name.to_string());
+                }
+            }
+        }
+
+        if let Some(block) = &mut self.block {
+            let args: Vec<&Spanned<&str>> = predicates
+                .iter()
+                .flat_map(|(_, args, _)| args.iter())
+                .collect();
+            let is_freed = |arg: &str| block.vars.iter().any(|(name, ..)| name == arg);
+            let constants: Vec<&Spanned<&str>> = args
+                .iter()
+                .copied()
+                .filter(|arg| !is_freed(arg.as_inner()))
+                .collect();
+
+            for (name, _, used) in block.vars.iter_mut() {
+                let occurences: Vec<&&Spanned<&str>> =
+                    args.iter().filter(|arg| arg.as_inner() == name).collect();
+                *used |= !occurences.is_empty();
+
+                if let [occurence] = occurences.as_slice() {
+                    // Maybe another occurence was mistyped
+                    let suggestion =
+                        closest_match(name, constants.iter().map(|constant| *constant.as_inner()))
+                            .and_then(|similar| constants.iter().find(|c| c.as_inner() == &similar))
+                            .map(|constant| (constant.as_inner().to_string(), constant.span()));
+
+                    warnings.push(Warning::SingletonVariable {
+                        name: name.clone(),
+                        span: occurence.span(),
+                        suggestion,
+                    });
+                }
+            }
+
+            for constant in constants {
+                block.constants.push(constant.as_inner().to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::SourceMap;
+    use crate::parser::parser::Parser;
+    use crate::parser::symbol::Line;
+    use crate::repl::Context;
+
+    /// Check every rule of a program, returning the warnings of the last one
+    fn check(program: &str) -> Vec<Warning> {
+        let mut sources = SourceMap::default();
+        let source = sources.add(Context::Repl);
+        let mut linter = SemanticLinter::default();
+        let mut warnings = vec![];
+        for line in program.lines() {
+            let location = sources.push_line(source, line);
+            warnings.clear();
+            let statement = Parser::new(line)
+                .line(&mut vec![])
+                .unwrap_or_else(|_| panic!("{:?} should parse", line));
+            let Some(Line::Rule(_, is_question, and_chains)) =
+                statement.line.map(Spanned::into_inner)
+            else {
+                panic!("{:?} should be a rule", line);
+            };
+            linter.check_rule(&and_chains, is_question, location, &mut warnings);
+        }
+        warnings
+    }
+
+    #[test]
+    fn undefined_predicates() {
+        let warnings = check("true => canfly(tweety)\nX? => cnafly(tweety) => X?");
+        let [Warning::UndefinedPredicate {
+            name, suggestion, ..
+        }] = warnings.as_slice()
+        else {
+            panic!("expected a single undefined predicate");
+        };
+        assert_eq!(name, "cnafly");
+        assert_eq!(suggestion.as_deref(), Some("canfly"));
+
+        // Nothing is suggested for names that are too different
+        let warnings = check("true => canfly(tweety)\nX? => swims(tweety) => X?");
+        assert!(matches!(
+            warnings.as_slice(),
+            [Warning::UndefinedPredicate {
+                suggestion: None,
+                ..
+            }]
+        ));
+
+        assert!(check("true => canfly(tweety)\nX? => canfly(tweety) => X?").is_empty());
+    }
+
+    #[test]
+    fn arity_mismatch() {
+        let warnings = check("true => größe(x)\ntrue => größe(x, y)");
+        let [Warning::ArityMismatch {
+            name,
+            arity,
+            expected,
+            first_use,
+            span,
+            suggestion,
+        }] = warnings.as_slice()
+        else {
+            panic!("expected a single arity mismatch");
+        };
+        assert_eq!(name, "größe");
+        assert_eq!((*arity, *expected), (2, 1));
+        assert_eq!(first_use.0.line, 1);
+        // Spans are character positions, even after non-ASCII names
+        assert_eq!((first_use.1 .0, first_use.1 .1), (8, 16));
+        assert_eq!((span.0, span.1), (8, 19));
+        assert_eq!(*suggestion, None);
+
+        // A predicate with a similar name and the right arity is suggested
+        let warnings = check("true => owns(x, y)\ntrue => own(x)\ntrue => own(x, y)");
+        let [Warning::ArityMismatch { suggestion, .. }] = warnings.as_slice() else {
+            panic!("expected a single arity mismatch");
+        };
+        assert_eq!(suggestion.as_deref(), Some("owns"));
+    }
+}
@@ -2,7 +2,7 @@
 //! Warnings are recoverable, meaning they are shown to the user
 //! but don't affect execution of the program.
 
-use crate::diagnostics::{Annotation, AnnotationType, Diagnostic};
+use crate::diagnostics::{Annotation, AnnotationType, Diagnostic, Location, Suggestion};
 use crate::parser::span::Span;
 
 pub enum Warning {
@@ -10,9 +10,13 @@ pub enum Warning {
         ident: String,
         first_declaration: Span,
         second_declaration: Span,
+        /// The second declaration, including the comma before it
+        removal: Span,
     },
     RedundantTrue {
         span: Span,
+        /// The 'true', including the 'and' that joins it to its neighbour
+        removal: Span,
     },
     NullifyingFalse {
         span: Span,
@@ -23,6 +27,95 @@ pub enum Warning {
     PurelyLiteralClause {
         span: Span,
     },
+    UnknownLint {
+        name: String,
+        span: Span,
+    },
+    MalformedPragma {
+        span: Span,
+    },
+    UndefinedPredicate {
+        name: String,
+        span: Span,
+        /// A defined predicate with a similar name
+        suggestion: Option<String>,
+    },
+    ArityMismatch {
+        name: String,
+        arity: usize,
+        expected: usize,
+        /// Where the predicate was first used
+        first_use: (Location, Span),
+        span: Span,
+        /// A predicate with a similar name and the right number of arguments
+        suggestion: Option<String>,
+    },
+    SingletonVariable {
+        name: String,
+        span: Span,
+        /// A constant in the same rule with a similar name, which might
+        /// have been meant to be the variable
+        suggestion: Option<(String, Span)>,
+    },
+    UnusedForallVariable {
+        name: String,
+        span: Span,
+        /// The forall statement, which might have been a few lines
+        /// before the warning was raised
+        location: Location,
+        /// A constant used in the forall block with a similar name
+        suggestion: Option<String>,
+    },
+}
+
+/// The names of all warnings, used to configure their lint levels
+pub const LINT_NAMES: [&str; 11] = [
+    "duplicate_scoped_variable",
+    "redundant_true",
+    "nullifying_false",
+    "redundant_false",
+    "purely_literal_clause",
+    "unknown_lints",
+    "malformed_pragma",
+    "undefined_predicate",
+    "arity_mismatch",
+    "singleton_variable",
+    "unused_forall_variable",
+];
+
+impl Warning {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::DuplicateScopedVariable { .. } => "W001",
+            Warning::RedundantTrue { .. } => "W002",
+            Warning::NullifyingFalse { .. } => "W003",
+            Warning::RedundantFalse { .. } => "W004",
+            Warning::PurelyLiteralClause { .. } => "W005",
+            Warning::UnknownLint { .. } => "W006",
+            Warning::MalformedPragma { .. } => "W007",
+            Warning::UndefinedPredicate { .. } => "W008",
+            Warning::ArityMismatch { .. } => "W009",
+            Warning::SingletonVariable { .. } => "W010",
+            Warning::UnusedForallVariable { .. } => "W011",
+        }
+    }
+
+    /// The name of the lint that controls this warning, one of [LINT_NAMES]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Warning::DuplicateScopedVariable { .. } => "duplicate_scoped_variable",
+            Warning::RedundantTrue { .. } => "redundant_true",
+            Warning::NullifyingFalse { .. } => "nullifying_false",
+            Warning::RedundantFalse { .. } => "redundant_false",
+            Warning::PurelyLiteralClause { .. } => "purely_literal_clause",
+            Warning::UnknownLint { .. } => "unknown_lints",
+            Warning::MalformedPragma { .. } => "malformed_pragma",
+            Warning::UndefinedPredicate { .. } => "undefined_predicate",
+            Warning::ArityMismatch { .. } => "arity_mismatch",
+            Warning::SingletonVariable { .. } => "singleton_variable",
+            Warning::UnusedForallVariable { .. } => "unused_forall_variable",
+        }
+    }
 }
 
 impl<'a> From<(&'a Warning, &'a str)> for Diagnostic<'a> {
@@ -32,38 +125,154 @@ impl<'a> From<(&'a Warning, &'a str)> for Diagnostic<'a> {
                 ident,
                 first_declaration,
                 second_declaration,
+                ..
             } => vec![
                 Annotation {
                     annotation_type: AnnotationType::Info,
                     span: *first_declaration,
                     msg: format!("{:?} is first declared here", ident),
+                    location: None,
                 },
                 Annotation {
                     annotation_type: AnnotationType::Info,
                     span: *second_declaration,
                     msg: "then declared a second time here".to_owned(),
+                    location: None,
                 },
             ],
-            Warning::RedundantTrue { span } => vec![Annotation {
+            Warning::RedundantTrue { span, .. } => vec![Annotation {
                 annotation_type: AnnotationType::Info,
                 span: *span,
                 msg: "'true' in this position does nothing".to_owned(),
+                location: None,
             }],
             Warning::NullifyingFalse { span } => vec![Annotation {
                 annotation_type: AnnotationType::Info,
                 span: *span,
                 msg: "this 'false' makes the entire and-chain false, preventing any conclusions about the other atoms".to_owned(),
+                location: None,
             }],
             Warning::RedundantFalse { span } => vec![Annotation {
                 annotation_type: AnnotationType::Info,
                 span: *span,
                 msg: "this 'false' can imply anything".to_owned(),
+                location: None,
             }],
             Warning::PurelyLiteralClause { span } => vec![Annotation {
                 annotation_type: AnnotationType::Info,
                 span: *span,
                 msg: "this clause does not contain any variables - and is therefore useless".to_owned(),
+                location: None,
+            }],
+            Warning::UnknownLint { name, span } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: format!("{:?} is not the name of a warning", name),
+                location: None,
             }],
+            Warning::MalformedPragma { span } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: "this pragma is ignored".to_owned(),
+                location: None,
+            }],
+            Warning::UndefinedPredicate {
+                span, suggestion, ..
+            } => {
+                let mut annotations = vec![Annotation {
+                    annotation_type: AnnotationType::Info,
+                    span: *span,
+                    msg: "no clause says anything about this predicate".to_owned(),
+                    location: None,
+                }];
+                if let Some(suggestion) = suggestion {
+                    annotations.push(Annotation {
+                        annotation_type: AnnotationType::Help,
+                        span: *span,
+                        msg: format!("did you mean {:?}?", suggestion),
+                        location: None,
+                    });
+                }
+                annotations
+            }
+            Warning::ArityMismatch {
+                arity,
+                expected,
+                first_use,
+                span,
+                suggestion,
+                ..
+            } => {
+                let mut annotations = vec![
+                    Annotation {
+                        annotation_type: AnnotationType::Info,
+                        span: *span,
+                        msg: format!("used with {} argument(s) here", arity),
+                        location: None,
+                    },
+                    Annotation {
+                        annotation_type: AnnotationType::Note,
+                        span: first_use.1,
+                        msg: format!("first used with {} argument(s) here", expected),
+                        location: Some(first_use.0),
+                    },
+                ];
+                if let Some(suggestion) = suggestion {
+                    annotations.push(Annotation {
+                        annotation_type: AnnotationType::Help,
+                        span: *span,
+                        msg: format!("did you mean {:?}?", suggestion),
+                        location: None,
+                    });
+                }
+                annotations
+            }
+            Warning::SingletonVariable {
+                name,
+                span,
+                suggestion,
+            } => {
+                let mut annotations = vec![Annotation {
+                    annotation_type: AnnotationType::Info,
+                    span: *span,
+                    msg: format!("{:?} is not used anywhere else in this rule", name),
+                    location: None,
+                }];
+                if let Some((constant, constant_span)) = suggestion {
+                    annotations.push(Annotation {
+                        annotation_type: AnnotationType::Help,
+                        span: *constant_span,
+                        msg: format!("{:?} is a constant, did you mean {:?}?", constant, name),
+                        location: None,
+                    });
+                }
+                annotations
+            }
+            Warning::UnusedForallVariable {
+                name,
+                span,
+                location,
+                suggestion,
+            } => {
+                let mut annotations = vec![Annotation {
+                    annotation_type: AnnotationType::Info,
+                    span: *span,
+                    msg: format!("{:?} is freed here", name),
+                    location: Some(*location),
+                }];
+                if let Some(constant) = suggestion {
+                    annotations.push(Annotation {
+                        annotation_type: AnnotationType::Help,
+                        span: *span,
+                        msg: format!(
+                            "the block uses the constant {:?}, did you mean {:?}?",
+                            constant, name
+                        ),
+                        location: Some(*location),
+                    });
+                }
+                annotations
+            }
         };
 
         let msg = match other.0 {
@@ -74,6 +283,21 @@ impl<'a> From<(&'a Warning, &'a str)> for Diagnostic<'a> {
             Warning::NullifyingFalse { .. } => "Nullifying 'false'".to_owned(),
             Warning::RedundantFalse { .. } => "Meaningless implication".to_owned(),
             Warning::PurelyLiteralClause { .. } => "Purely literal clause".to_owned(),
+            Warning::UnknownLint { name, .. } => format!("Unknown lint {:?}", name),
+            Warning::MalformedPragma { .. } => "Malformed pragma".to_owned(),
+            Warning::UndefinedPredicate { name, .. } => {
+                format!("Predicate {:?} is never defined", name)
+            }
+            Warning::ArityMismatch { name, expected, .. } => format!(
+                "Predicate {:?} was previously used with {} argument(s)",
+                name, expected
+            ),
+            Warning::SingletonVariable { name, .. } => {
+                format!("Free variable {:?} only appears once", name)
+            }
+            Warning::UnusedForallVariable { name, .. } => {
+                format!("Free variable {:?} is never used", name)
+            }
         };
 
         let note = match other.0 {
@@ -84,15 +308,48 @@ impl<'a> From<(&'a Warning, &'a str)> for Diagnostic<'a> {
             Warning::NullifyingFalse { .. } => None,
             Warning::RedundantFalse { .. } => Some("'false' implies both 'true' and 'false'"),
             Warning::PurelyLiteralClause { .. } => None,
+            Warning::UnknownLint { .. } => {
+                Some("run 'leuchtkraft --explain W006' for a list of lints")
+            }
+            Warning::MalformedPragma { .. } => {
+                Some("pragmas look like '// leuchtkraft: allow(redundant_true, redundant_false)'")
+            }
+            Warning::UndefinedPredicate { .. } => {
+                Some("nothing can be concluded about predicates that no clause mentions")
+            }
+            Warning::ArityMismatch { .. } => {
+                Some("predicates with different numbers of arguments are unrelated")
+            }
+            Warning::SingletonVariable { .. } => {
+                Some("a variable that is used only once does not relate anything to each other")
+            }
+            Warning::UnusedForallVariable { .. } => None,
+        };
+
+        let suggestions = match other.0 {
+            Warning::DuplicateScopedVariable { ident, removal, .. } => vec![Suggestion {
+                msg: format!("remove the second declaration of {:?}", ident),
+                span: *removal,
+                replacement: String::new(),
+                location: None,
+            }],
+            Warning::RedundantTrue { removal, .. } => vec![Suggestion {
+                msg: "remove the 'true'".to_owned(),
+                span: *removal,
+                replacement: String::new(),
+                location: None,
+            }],
+            _ => vec![],
         };
 
         Self {
-            code: None,
+            code: Some(other.0.code()),
             buffer: other.1,
             annotation_type: AnnotationType::Warning,
-            annotations: annotations,
-            msg: msg,
-            note: note,
+            annotations,
+            msg,
+            note,
+            suggestions,
         }
     }
 }
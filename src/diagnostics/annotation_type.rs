@@ -1,6 +1,5 @@
 use std::fmt;
 
-#[allow(dead_code)] // Some annotation types won't be used until later
 /// Different types of annotations, usually only distinguishable by Color
 pub enum AnnotationType {
     /// Error messages that should only be generated by unrecoverable errors
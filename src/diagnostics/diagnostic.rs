@@ -1,17 +1,73 @@
 use super::annotation_type::AnnotationType;
+use super::source_map::{Location, SourceMap};
 use crate::parser::span::Span;
 
 pub struct Annotation {
     pub annotation_type: AnnotationType,
     pub span: Span,
     pub msg: String,
+    /// The line the annotation refers to, `None` for the line of the diagnostic itself
+    pub location: Option<Location>,
+}
+
+/// A change to the code that fixes a diagnostic, and can be applied
+/// without any human intervention
+#[derive(Clone)]
+pub struct Suggestion {
+    pub msg: String,
+    /// The text to replace
+    pub span: Span,
+    pub replacement: String,
+    /// The line the suggestion applies to, `None` for the line of the diagnostic itself
+    pub location: Option<Location>,
+}
+
+impl Suggestion {
+    /// Apply the suggestion to the line it refers to
+    pub fn apply(&self, line: &str) -> String {
+        let mut chars = line.chars();
+        let mut fixed: String = chars.by_ref().take(self.span.0).collect();
+        fixed.push_str(&self.replacement);
+        fixed.extend(chars.skip(self.span.1 - self.span.0));
+        fixed
+    }
 }
 
 pub struct Diagnostic<'a> {
-    pub code: Option<usize>,
+    /// A permanent code like `E001`, see [crate::debug::explain]
+    pub code: Option<&'static str>,
     pub buffer: &'a str,
     pub annotation_type: AnnotationType,
     pub annotations: Vec<Annotation>,
     pub msg: String,
     pub note: Option<&'a str>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic<'_> {
+    /// The location of an annotation, given the location of the diagnostic
+    pub fn annotation_location(annotation: &Annotation, location: Location) -> Location {
+        annotation.location.unwrap_or(location)
+    }
+
+    /// The location the diagnostic is reported at.
+    /// This is usually the location of the diagnostic itself, unless
+    /// all annotations refer to other lines.
+    pub fn primary_location(&self, location: Location) -> Location {
+        match self.annotations.first() {
+            Some(first) if self.annotations.iter().all(|a| a.location.is_some()) => {
+                Self::annotation_location(first, location)
+            }
+            _ => location,
+        }
+    }
+
+    /// The text of a line the diagnostic refers to
+    pub fn line<'s>(&'s self, at: Location, location: Location, sources: &'s SourceMap) -> &'s str {
+        if at == location {
+            self.buffer
+        } else {
+            sources.line(at).unwrap_or_default()
+        }
+    }
 }
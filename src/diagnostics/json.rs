@@ -0,0 +1,227 @@
+//! Machine-readable diagnostics.
+//!
+//! Every diagnostic is serialized as a single JSON object on its own line:
+//!
+//! ```text
+//! {
+//!   "type": "diagnostic",
+//!   "severity": "error" | "warning",
+//!   "code": "E001" | null,
+//!   "message": string,
+//!   "file": string | null,          // null for code typed into the repl
+//!   "line": number,                 // 1-based
+//!   "source": string,               // the text of the line, as written in the file
+//!   "annotations": [{
+//!     "severity": "info" | "note" | "help" | ...,
+//!     "message": string,
+//!     "file": string | null,        // annotations may refer to other lines or files
+//!     "line": number,
+//!     "source": string,
+//!     "byte_start": number,         // 0-based, relative to the start of the line
+//!     "byte_end": number,           // exclusive
+//!     "column_start": number,       // 1-based, in characters
+//!     "column_end": number          // exclusive
+//!   }],
+//!   "note": string | null,
+//!   "suggestions": [{               // fixes that can be applied automatically
+//!     "message": string,
+//!     "file": string | null,
+//!     "line": number,
+//!     "byte_start": number,         // the text to replace, like in annotations
+//!     "byte_end": number,
+//!     "replacement": string
+//!   }]
+//! }
+//! ```
+//!
+//! New fields may be added in the future, but existing ones won't change.
+
+use super::diagnostic::Diagnostic;
+use super::source_map::{expand_tabs, original_position, Location, SourceMap};
+use crate::json::Json;
+use crate::repl::Context;
+
+/// Convert a char offset within a line to a byte offset.
+/// Offsets past the end of the line (like the position of a missing token)
+/// are counted as if the line was padded with spaces.
+fn byte_offset(line: &str, offset: usize) -> usize {
+    match line.char_indices().nth(offset) {
+        Some((byte, _)) => byte,
+        None => line.len() + offset - line.chars().count(),
+    }
+}
+
+/// The line as it was written, which may contain tabs.
+/// Falls back to the executed line if the source map doesn't know it.
+fn original_line<'s>(executed: &'s str, at: Location, sources: &'s SourceMap) -> &'s str {
+    sources
+        .original_line(at)
+        .filter(|original| expand_tabs(original) == executed)
+        .unwrap_or(executed)
+}
+
+/// The byte offset and the 0-based column in the original line,
+/// for a position in the executed line
+fn offsets(line: &str, position: usize) -> (usize, usize) {
+    let column = original_position(line, position);
+    (byte_offset(line, column), column)
+}
+
+fn file(location: Location, sources: &SourceMap) -> Json {
+    match sources.context(location.source) {
+        Context::Repl | Context::Query | Context::Stdin => Json::Null,
+        Context::File(path) => path.display().to_string().into(),
+    }
+}
+
+impl Diagnostic<'_> {
+    pub fn to_json(&self, location: Location, sources: &SourceMap) -> Json {
+        let annotations = self
+            .annotations
+            .iter()
+            .map(|annotation| {
+                let at = Self::annotation_location(annotation, location);
+                let line = original_line(self.line(at, location, sources), at, sources);
+                let (byte_start, column_start) = offsets(line, annotation.span.0);
+                let (byte_end, column_end) = offsets(line, annotation.span.1);
+                Json::object([
+                    ("severity", annotation.annotation_type.to_string().into()),
+                    ("message", annotation.msg.as_str().into()),
+                    ("file", file(at, sources)),
+                    ("line", at.line.into()),
+                    ("source", line.into()),
+                    ("byte_start", byte_start.into()),
+                    ("byte_end", byte_end.into()),
+                    ("column_start", (column_start + 1).into()),
+                    ("column_end", (column_end + 1).into()),
+                ])
+            })
+            .collect();
+
+        let suggestions = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let at = suggestion.location.unwrap_or(location);
+                let line = original_line(self.line(at, location, sources), at, sources);
+                let (byte_start, _) = offsets(line, suggestion.span.0);
+                let (byte_end, _) = offsets(line, suggestion.span.1);
+                Json::object([
+                    ("message", suggestion.msg.as_str().into()),
+                    ("file", file(at, sources)),
+                    ("line", at.line.into()),
+                    ("byte_start", byte_start.into()),
+                    ("byte_end", byte_end.into()),
+                    ("replacement", suggestion.replacement.as_str().into()),
+                ])
+            })
+            .collect();
+
+        let primary = self.primary_location(location);
+        Json::object([
+            ("type", "diagnostic".into()),
+            ("severity", self.annotation_type.to_string().into()),
+            ("code", self.code.into()),
+            ("message", self.msg.as_str().into()),
+            ("file", file(primary, sources)),
+            ("line", primary.line.into()),
+            (
+                "source",
+                original_line(self.line(primary, location, sources), primary, sources).into(),
+            ),
+            ("annotations", Json::Array(annotations)),
+            ("note", self.note.into()),
+            ("suggestions", Json::Array(suggestions)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::{expand_tabs, DisplayDiagnostic, MessageFormat};
+    use crate::interpreter::Interpreter;
+    use crate::json::Json;
+    use crate::repl::Context;
+    use std::path::PathBuf;
+    use termcolor::Buffer;
+
+    /// Execute a program like a script would and emit the first warning
+    /// of its last line as JSON
+    fn last_warning(program: &str) -> Json {
+        let mut i = Interpreter::new();
+        let source = i.add_source(Context::File(PathBuf::from("test.le")));
+        let mut lines = vec![];
+        for line in program.lines() {
+            let location = i.push_line(source, line);
+            let executed = expand_tabs(line);
+            let mut warnings = vec![];
+            assert!(i.execute(&executed, location, &mut warnings).is_ok());
+            lines.push((location, executed, warnings));
+        }
+        let (location, line, warnings) = lines.last().unwrap();
+        let diagnostic = i.lint(&warnings[0], line).unwrap();
+
+        let mut writer = Buffer::no_color();
+        writer
+            .emit(diagnostic, *location, i.sources(), MessageFormat::Json)
+            .unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        Json::parse(&output).unwrap()
+    }
+
+    fn field(json: &Json, key: &str) -> Json {
+        json.get(key).cloned().unwrap()
+    }
+
+    /// Apply the first suggestion to the line, like an editor would
+    fn apply_suggestion(json: &Json, line: &str) -> String {
+        let suggestions = field(json, "suggestions");
+        let suggestion = &suggestions.as_array().unwrap()[0];
+        let byte_start = field(suggestion, "byte_start").as_usize().unwrap();
+        let byte_end = field(suggestion, "byte_end").as_usize().unwrap();
+        let replacement = field(suggestion, "replacement");
+        format!(
+            "{}{}{}",
+            &line[..byte_start],
+            replacement.as_str().unwrap(),
+            &line[byte_end..]
+        )
+    }
+
+    #[test]
+    fn emitted_diagnostics_can_be_parsed() {
+        let line = "true => größe(x) and true";
+        let json = last_warning(line);
+        assert_eq!(field(&json, "type"), "diagnostic".into());
+        assert_eq!(field(&json, "severity"), "warning".into());
+        assert_eq!(field(&json, "code"), "W002".into());
+        assert_eq!(field(&json, "file"), "test.le".into());
+        assert_eq!(field(&json, "line"), 1_usize.into());
+        assert_eq!(field(&json, "source"), line.into());
+
+        // The 'true' at the end comes after two characters that take up two bytes each
+        let annotations = field(&json, "annotations");
+        let annotation = &annotations.as_array().unwrap()[0];
+        let start = line.rfind("true").unwrap();
+        assert_eq!(field(annotation, "byte_start"), start.into());
+        assert_eq!(field(annotation, "column_start"), (start - 2 + 1).into());
+
+        assert_eq!(apply_suggestion(&json, line), "true => größe(x)");
+    }
+
+    #[test]
+    fn offsets_refer_to_the_line_with_tabs() {
+        let json = last_warning("forall X\n\tbird(X) and true => canfly(X)");
+        let line = "\tbird(X) and true => canfly(X)";
+        assert_eq!(field(&json, "source"), line.into());
+
+        let annotations = field(&json, "annotations");
+        let annotation = &annotations.as_array().unwrap()[0];
+        let start = line.find("true").unwrap();
+        assert_eq!(field(annotation, "byte_start"), start.into());
+        assert_eq!(field(annotation, "column_start"), (start + 1).into());
+
+        assert_eq!(apply_suggestion(&json, line), "\tbird(X) => canfly(X)");
+    }
+}
@@ -4,8 +4,11 @@
 /// and determined that i hate them both to an about equal degree.
 mod annotation_type;
 mod diagnostic;
+mod json;
 mod render;
+mod source_map;
 
 pub use annotation_type::AnnotationType;
-pub use diagnostic::{Annotation, Diagnostic};
-pub use render::DisplayDiagnostic;
+pub use diagnostic::{Annotation, Diagnostic, Suggestion};
+pub use render::{DisplayDiagnostic, MessageFormat};
+pub use source_map::{expand_tabs, original_position, Location, SourceId, SourceMap};
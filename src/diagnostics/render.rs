@@ -1,7 +1,8 @@
 use termcolor::{Color, ColorSpec, WriteColor};
 
 use super::annotation_type::AnnotationType;
-use super::diagnostic::Diagnostic;
+use super::diagnostic::{Annotation, Diagnostic};
+use super::source_map::{Location, SourceMap};
 use crate::repl::Context;
 use std::io;
 
@@ -18,83 +19,202 @@ pub fn annotation_color(annotation_type: &AnnotationType) -> ColorSpec {
     spec
 }
 
+#[derive(Clone, Copy, Default, PartialEq)]
+/// How diagnostics are presented to the user
+pub enum MessageFormat {
+    /// Colored, human-readable snippets
+    #[default]
+    Human,
+    /// One JSON object per line, see [super::json]
+    Json,
+}
+
+/// A quoted line together with the annotations that point into it
+struct Snippet<'d> {
+    location: Location,
+    code: &'d str,
+    annotations: Vec<&'d Annotation>,
+}
+
+/// Group the annotations of a diagnostic by the line they refer to.
+/// Snippets from the source of the diagnostic come first, all of them are
+/// sorted by line.
+fn snippets<'d>(
+    diagnostic: &'d Diagnostic,
+    location: Location,
+    sources: &'d SourceMap,
+) -> Vec<Snippet<'d>> {
+    let mut snippets: Vec<Snippet> = vec![];
+    for annotation in &diagnostic.annotations {
+        let at = Diagnostic::annotation_location(annotation, location);
+        match snippets.iter_mut().find(|snippet| snippet.location == at) {
+            Some(snippet) => snippet.annotations.push(annotation),
+            None => snippets.push(Snippet {
+                location: at,
+                code: diagnostic.line(at, location, sources),
+                annotations: vec![annotation],
+            }),
+        }
+    }
+    if snippets.is_empty() {
+        snippets.push(Snippet {
+            location,
+            code: diagnostic.buffer,
+            annotations: vec![],
+        });
+    }
+    snippets.sort_by_key(|snippet| {
+        let at = snippet.location;
+        (at.source != location.source, at.source, at.line)
+    });
+    snippets
+}
+
 pub trait DisplayDiagnostic {
     fn render(
         &mut self,
         diagnostic: Diagnostic,
-        lineno: usize,
-        context: &Context,
+        location: Location,
+        sources: &SourceMap,
+    ) -> io::Result<()>;
+
+    /// Write a diagnostic in the given format
+    fn emit(
+        &mut self,
+        diagnostic: Diagnostic,
+        location: Location,
+        sources: &SourceMap,
+        format: MessageFormat,
     ) -> io::Result<()>;
 }
 
 impl<W: WriteColor> DisplayDiagnostic for W {
+    fn emit(
+        &mut self,
+        diagnostic: Diagnostic,
+        location: Location,
+        sources: &SourceMap,
+        format: MessageFormat,
+    ) -> io::Result<()> {
+        match format {
+            MessageFormat::Human => self.render(diagnostic, location, sources),
+            MessageFormat::Json => writeln!(self, "{}", diagnostic.to_json(location, sources)),
+        }
+    }
+
     fn render(
         &mut self,
         diagnostic: Diagnostic,
-        lineno: usize,
-        context: &Context,
+        location: Location,
+        sources: &SourceMap,
     ) -> io::Result<()> {
         // ================= Diagnostic Message ===========
-        // "Error[001]: Forgot to implement the important thing
+        // "error[E001]: Forgot to implement the important thing
         self.set_color(annotation_color(&diagnostic.annotation_type).set_bold(true))?;
         write!(self, "{}", diagnostic.annotation_type)?;
 
         if let Some(code) = diagnostic.code {
-            write!(self, "[{:03}]", code)?;
+            write!(self, "[{}]", code)?;
         }
 
         // Message is written in bold white
         self.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
         writeln!(self, ": {}", diagnostic.msg)?;
 
-        // Context indentation depends on the line number
+        let snippets = snippets(&diagnostic, location, sources);
+
+        // Context indentation depends on the largest line number
         // TODO: replace with log10 + 1 once the integer_log features gets stabilized
-        let lineno_len = format!("{}", lineno).len();
+        let suggestion_lines = diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.location.unwrap_or(location).line);
+        let max_lineno = snippets
+            .iter()
+            .map(|s| s.location.line)
+            .chain(suggestion_lines)
+            .max()
+            .unwrap_or(0);
+        let lineno_len = format!("{}", max_lineno).len();
+        let gutter = " ".repeat(lineno_len);
 
-        // =========== Quote Header ===============
-        // Context is written as blue "-->" with the info afterwards
-        self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
-        write!(self, "{}--> ", " ".repeat(lineno_len))?;
-        self.reset()?;
+        let primary = diagnostic.primary_location(location);
+        let mut previous: Option<Location> = None;
+        for snippet in &snippets {
+            let at = snippet.location;
+            match previous {
+                // =========== Quote Header ===============
+                // Context is written as blue "-->" (or ":::" for other sources)
+                // with the info afterwards
+                Some(previous) if previous.source == at.source => {
+                    // Elide the lines between two snippets
+                    self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                    match at.line - previous.line {
+                        1 => {}
+                        2 => {
+                            let between = Location {
+                                source: at.source,
+                                line: at.line - 1,
+                            };
+                            write!(self, "{:>width$} | ", between.line, width = lineno_len)?;
+                            self.reset()?;
+                            writeln!(self, "{}", sources.line(between).unwrap_or_default())?;
+                        }
+                        _ => writeln!(self, "...")?,
+                    }
+                }
+                _ => {
+                    let arrow = if previous.is_none() { "-->" } else { ":::" };
+                    self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                    write!(self, "{}{} ", gutter, arrow)?;
+                    self.reset()?;
 
-        match context {
-            Context::Repl => write!(self, "Repl")?,
-            Context::File(path) => write!(self, "{}", path.display())?,
-        }
-        writeln!(self, ":{}", lineno)?;
-        self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                    match sources.context(at.source) {
+                        Context::Repl => write!(self, "Repl")?,
+                        Context::Query => write!(self, "Query")?,
+                        Context::Stdin => write!(self, "<stdin>")?,
+                        Context::File(path) => write!(self, "{}", path.display())?,
+                    }
+                    // The first header points at the diagnostic itself
+                    let line = match previous {
+                        None if primary.source == at.source => primary.line,
+                        _ => at.line,
+                    };
+                    writeln!(self, ":{}", line)?;
+                    self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                    writeln!(self, "{} |", gutter)?;
+                }
+            }
+            previous = Some(at);
 
-        // =========== Quoted Code ===============
-        writeln!(self, "{} |", " ".repeat(lineno_len))?;
-        write!(self, "{} | ", lineno)?;
+            // =========== Quoted Code ===============
+            self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+            write!(self, "{:>width$} | ", at.line, width = lineno_len)?;
 
-        // Quoted code is writtern in default color
-        self.reset()?;
-        writeln!(self, "{}", diagnostic.buffer)?;
+            // Quoted code is writtern in default color
+            self.reset()?;
+            writeln!(self, "{}", snippet.code)?;
 
-        for annotation in diagnostic.annotations {
-            self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
-            write!(self, "{} | ", " ".repeat(lineno_len))?;
-
-            self.set_color(&annotation_color(&annotation.annotation_type))?;
-            write!(
-                self,
-                "{}",
-                " ".repeat(diagnostic.buffer[..annotation.span.0].len())
-            )?;
-            write!(
-                self,
-                "{}-- ",
-                "^".repeat(annotation.span.1 - annotation.span.0)
-            )?;
-            writeln!(self, "{}", annotation.msg)?;
+            for annotation in &snippet.annotations {
+                self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                write!(self, "{} | ", gutter)?;
+
+                self.set_color(&annotation_color(&annotation.annotation_type))?;
+                write!(self, "{}", " ".repeat(annotation.span.0))?;
+                write!(
+                    self,
+                    "{}-- ",
+                    "^".repeat(annotation.span.1 - annotation.span.0)
+                )?;
+                writeln!(self, "{}", annotation.msg)?;
+            }
         }
 
         self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
 
         // Add the note (if any)
         if let Some(note) = diagnostic.note {
-            write!(self, "{} =", " ".repeat(lineno_len))?;
+            write!(self, "{} =", gutter)?;
 
             self.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
             write!(self, "note: ")?;
@@ -102,8 +222,200 @@ impl<W: WriteColor> DisplayDiagnostic for W {
             self.set_color(&annotation_color(&AnnotationType::Note))?;
             writeln!(self, "{}", &note)?;
         } else {
-            writeln!(self, "{} |", " ".repeat(lineno_len))?;
+            writeln!(self, "{} |", gutter)?;
+        }
+
+        // Show the code as it would look like after applying each suggestion
+        // "help: remove the 'true'"
+        for suggestion in &diagnostic.suggestions {
+            let at = suggestion.location.unwrap_or(location);
+            let line = diagnostic.line(at, location, sources);
+
+            self.set_color(annotation_color(&AnnotationType::Help).set_bold(true))?;
+            write!(self, "help")?;
+            self.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
+            writeln!(self, ": {}", suggestion.msg)?;
+
+            self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+            writeln!(self, "{} |", gutter)?;
+            write!(self, "{:>width$} | ", at.line, width = lineno_len)?;
+            self.reset()?;
+            writeln!(self, "{}", suggestion.apply(line))?;
+
+            // Mark inserted text
+            let inserted = suggestion.replacement.chars().count();
+            if inserted != 0 {
+                self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+                write!(self, "{} | ", gutter)?;
+                self.set_color(&annotation_color(&AnnotationType::Help))?;
+                writeln!(
+                    self,
+                    "{}{}",
+                    " ".repeat(suggestion.span.0),
+                    "+".repeat(inserted)
+                )?;
+            }
+            self.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+            writeln!(self, "{} |", gutter)?;
         }
         self.reset()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::span::Span;
+    use std::path::PathBuf;
+    use termcolor::Buffer;
+
+    fn annotation(span: Span, msg: &str, location: Option<Location>) -> Annotation {
+        Annotation {
+            annotation_type: AnnotationType::Note,
+            span,
+            msg: msg.to_owned(),
+            location,
+        }
+    }
+
+    fn rendered(diagnostic: Diagnostic, location: Location, sources: &SourceMap) -> String {
+        let mut writer = Buffer::no_color();
+        writer.render(diagnostic, location, sources).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn elided_lines() {
+        let mut sources = SourceMap::default();
+        let source = sources.add(Context::File(PathBuf::from("birds.le")));
+        let lines = [
+            "forall X",
+            "    bird(X) => canfly(X)",
+            "",
+            "",
+            "",
+            "",
+            "true => bird(tweety)",
+        ];
+        let locations: Vec<Location> = lines
+            .iter()
+            .map(|line| sources.push_line(source, line))
+            .collect();
+        let diagnostic = Diagnostic {
+            code: Some("W000"),
+            buffer: lines[6],
+            annotation_type: AnnotationType::Warning,
+            annotations: vec![
+                annotation(Span(8, 20), "used here", None),
+                annotation(Span(0, 6), "freed here", Some(locations[0])),
+                annotation(Span(4, 11), "defined here", Some(locations[1])),
+            ],
+            msg: "Something".to_owned(),
+            note: None,
+            suggestions: vec![],
+        };
+        assert_eq!(
+            rendered(diagnostic, locations[6], &sources),
+            "\
+warning[W000]: Something
+ --> birds.le:7
+  |
+1 | forall X
+  | ^^^^^^-- freed here
+2 |     bird(X) => canfly(X)
+  |     ^^^^^^^-- defined here
+...
+7 | true => bird(tweety)
+  |         ^^^^^^^^^^^^-- used here
+  |
+"
+        );
+    }
+
+    #[test]
+    fn single_elided_line() {
+        let mut sources = SourceMap::default();
+        let source = sources.add(Context::Repl);
+        let first = sources.push_line(source, "forall X");
+        sources.push_line(source, "    a(X) => b(X)");
+        let third = sources.push_line(source, "    b(X) => c(x)");
+        let diagnostic = Diagnostic {
+            code: None,
+            buffer: "    b(X) => c(x)",
+            annotation_type: AnnotationType::Error,
+            annotations: vec![
+                annotation(Span(6, 7), "used here", None),
+                annotation(Span(7, 8), "freed here", Some(first)),
+            ],
+            msg: "Something".to_owned(),
+            note: Some("a note"),
+            suggestions: vec![],
+        };
+        // A single line between two snippets is quoted instead of elided
+        assert_eq!(
+            rendered(diagnostic, third, &sources),
+            "\
+error: Something
+ --> Repl:3
+  |
+1 | forall X
+  |        ^-- freed here
+2 |     a(X) => b(X)
+3 |     b(X) => c(x)
+  |       ^-- used here
+  =note: a note
+"
+        );
+    }
+
+    #[test]
+    fn multiple_sources() {
+        let mut sources = SourceMap::default();
+        let library = sources.add(Context::File(PathBuf::from("lib.le")));
+        sources.push_line(library, "module geo");
+        let definition = sources.push_line(library, "true => inside(berlin)");
+        let script = sources.add(Context::File(PathBuf::from("main.le")));
+        let location = sources.push_line(script, "X? => inside(berlin, germany) => X?");
+
+        let diagnostic = Diagnostic {
+            code: None,
+            buffer: "X? => inside(berlin, germany) => X?",
+            annotation_type: AnnotationType::Warning,
+            annotations: vec![
+                annotation(Span(6, 29), "used here", None),
+                annotation(Span(8, 22), "defined here", Some(definition)),
+            ],
+            msg: "Something".to_owned(),
+            note: None,
+            suggestions: vec![],
+        };
+        // The source of the diagnostic comes first, other sources are marked with ":::"
+        assert_eq!(
+            rendered(diagnostic, location, &sources),
+            "\
+warning: Something
+ --> main.le:1
+  |
+1 | X? => inside(berlin, germany) => X?
+  |       ^^^^^^^^^^^^^^^^^^^^^^^-- used here
+ ::: lib.le:2
+  |
+2 | true => inside(berlin)
+  |         ^^^^^^^^^^^^^^-- defined here
+  |
+"
+        );
+
+        // Diagnostics with annotations in other sources only are reported there
+        let diagnostic = Diagnostic {
+            code: None,
+            buffer: "X? => inside(berlin, germany) => X?",
+            annotation_type: AnnotationType::Warning,
+            annotations: vec![annotation(Span(8, 22), "defined here", Some(definition))],
+            msg: "Something".to_owned(),
+            note: None,
+            suggestions: vec![],
+        };
+        assert!(rendered(diagnostic, location, &sources).contains(" --> lib.le:2\n"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Keeps the code of every source around, so that diagnostics can quote
+//! any line of any file - not just the one that is currently executed.
+
+use crate::repl::Context;
+
+/// Identifies a source of code in a [SourceMap]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(usize);
+
+/// A line in a source
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub source: SourceId,
+    /// 1-based line number
+    pub line: usize,
+}
+
+/// Tabs are replaced with this before a line is executed
+const TAB: &str = "    ";
+
+/// Replace the tabs in a line, as they only mess up formatting later
+pub fn expand_tabs(line: &str) -> String {
+    line.replace('\t', TAB)
+}
+
+/// Convert a position in a line with expanded tabs back into a position in the original line.
+/// Positions inside of a tab are moved to its start, positions past the end of the line
+/// (like the position of a missing token) stay past it.
+pub fn original_position(line: &str, expanded: usize) -> usize {
+    let mut width = 0;
+    for (ix, c) in line.chars().enumerate() {
+        width += if c == '\t' { TAB.len() } else { 1 };
+        if width > expanded {
+            return ix;
+        }
+    }
+    line.chars().count() + expanded - width
+}
+
+struct Source {
+    context: Context,
+    /// The lines with expanded tabs, like they are executed
+    lines: Vec<String>,
+    /// The lines as they were written
+    originals: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+}
+
+impl SourceMap {
+    /// Register a new source, without any lines
+    pub fn add(&mut self, context: Context) -> SourceId {
+        self.sources.push(Source {
+            context,
+            lines: vec![],
+            originals: vec![],
+        });
+        SourceId(self.sources.len() - 1)
+    }
+
+    /// Append a line to a source, returning its location
+    pub fn push_line(&mut self, source: SourceId, line: &str) -> Location {
+        let source_lines = &mut self.sources[source.0];
+        source_lines.lines.push(expand_tabs(line));
+        source_lines.originals.push(line.to_owned());
+        Location {
+            source,
+            line: source_lines.lines.len(),
+        }
+    }
+
+    pub fn context(&self, source: SourceId) -> &Context {
+        &self.sources[source.0].context
+    }
+
+    /// The text of a line with expanded tabs, which all spans refer to
+    pub fn line(&self, location: Location) -> Option<&str> {
+        self.sources[location.source.0]
+            .lines
+            .get(location.line.checked_sub(1)?)
+            .map(String::as_str)
+    }
+
+    /// The text of a line as it was written, with tabs
+    pub fn original_line(&self, location: Location) -> Option<&str> {
+        self.sources[location.source.0]
+            .originals
+            .get(location.line.checked_sub(1)?)
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn positions_with_tabs() {
+        assert_eq!(original_position("\ta", 0), 0);
+        assert_eq!(original_position("\ta", 2), 0);
+        assert_eq!(original_position("\ta", 4), 1);
+        assert_eq!(original_position("\ta", 5), 2);
+        assert_eq!(original_position("\ta", 7), 4);
+        assert_eq!(original_position("a\tb", 1), 1);
+        assert_eq!(original_position("äb", 1), 1);
+    }
+
+    #[test]
+    fn original_lines() {
+        let mut sources = SourceMap::default();
+        let source = sources.add(Context::Repl);
+        let location = sources.push_line(source, "\ta(x)");
+        assert_eq!(sources.line(location), Some("    a(x)"));
+        assert_eq!(sources.original_line(location), Some("\ta(x)"));
+    }
+
+    #[test]
+    fn locations() {
+        let mut sources = SourceMap::default();
+        let repl = sources.add(Context::Repl);
+        let file = sources.add(Context::File(PathBuf::from("birds.le")));
+        let first = sources.push_line(repl, "true => bird(tweety)");
+        let other = sources.push_line(file, "forall X");
+        let second = sources.push_line(repl, "true => größe(ä)");
+
+        // Lines are numbered from 1 within each source
+        assert_eq!((first.source, first.line), (repl, 1));
+        assert_eq!((other.source, other.line), (file, 1));
+        assert_eq!((second.source, second.line), (repl, 2));
+        assert_eq!(sources.line(first), Some("true => bird(tweety)"));
+        assert_eq!(sources.line(other), Some("forall X"));
+        assert_eq!(sources.line(second), Some("true => größe(ä)"));
+
+        // Lines that weren't pushed (yet) are missing
+        let missing = Location {
+            source: file,
+            line: 2,
+        };
+        assert_eq!(sources.line(missing), None);
+        assert_eq!(sources.original_line(Location { line: 0, ..first }), None);
+
+        assert!(matches!(sources.context(repl), Context::Repl));
+        assert!(
+            matches!(sources.context(file), Context::File(path) if path == Path::new("birds.le"))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! The exit status of the process, so that shell scripts can tell
+//! different kinds of failures apart.
+
+use crate::diagnostics::Diagnostic;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExitStatus {
+    /// Everything went fine
+    Success = 0,
+    /// Any failure without a more specific status, like a denied warning
+    Error = 1,
+    /// A line could not be parsed
+    SyntaxError = 2,
+    /// The known clauses contradict each other
+    Contradiction = 3,
+    /// A question did not resolve to the values its `// expect:` comment expects
+    ExpectationFailed = 4,
+    /// A file could not be read or written
+    IoError = 5,
+}
+
+impl ExitStatus {
+    /// The status caused by reporting a diagnostic
+    pub fn of(diagnostic: &Diagnostic) -> Self {
+        match diagnostic.code {
+            // Unexpected tokens and unexpected indentation
+            Some("E001") | Some("E002") => ExitStatus::SyntaxError,
+            // An imported file could not be read
+            Some("E008") => ExitStatus::IoError,
+            _ => ExitStatus::Error,
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self == ExitStatus::Success
+    }
+
+    /// Combine two statuses, keeping the first failure
+    pub fn and(self, other: ExitStatus) -> Self {
+        if self.is_success() {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Exit the process with this status
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::AnnotationType;
+
+    fn diagnostic(code: Option<&'static str>) -> Diagnostic<'static> {
+        Diagnostic {
+            code,
+            buffer: "",
+            annotation_type: AnnotationType::Error,
+            annotations: vec![],
+            msg: String::new(),
+            note: None,
+            suggestions: vec![],
+        }
+    }
+
+    #[test]
+    fn first_failure_wins() {
+        use ExitStatus::*;
+        assert_eq!(Success.and(Success), Success);
+        assert_eq!(Success.and(IoError), IoError);
+        assert_eq!(SyntaxError.and(Success), SyntaxError);
+        assert_eq!(Contradiction.and(SyntaxError), Contradiction);
+        assert_eq!(Error.and(IoError).and(ExpectationFailed), Error);
+        assert_eq!(
+            Success.and(Success).and(ExpectationFailed),
+            ExpectationFailed
+        );
+    }
+
+    #[test]
+    fn status_of_diagnostics() {
+        assert_eq!(
+            ExitStatus::of(&diagnostic(Some("E001"))),
+            ExitStatus::SyntaxError
+        );
+        assert_eq!(
+            ExitStatus::of(&diagnostic(Some("E002"))),
+            ExitStatus::SyntaxError
+        );
+        assert_eq!(
+            ExitStatus::of(&diagnostic(Some("E008"))),
+            ExitStatus::IoError
+        );
+        assert_eq!(ExitStatus::of(&diagnostic(Some("E003"))), ExitStatus::Error);
+        assert_eq!(ExitStatus::of(&diagnostic(Some("W001"))), ExitStatus::Error);
+        assert_eq!(ExitStatus::of(&diagnostic(None)), ExitStatus::Error);
+    }
+}
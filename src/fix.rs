@@ -0,0 +1,126 @@
+//! Applies the suggestions attached to diagnostics, like removing a
+//! redundant 'true', directly to a script.
+
+use crate::debug::lint::LintLevels;
+use crate::diagnostics::{expand_tabs, original_position, Diagnostic, Location, Suggestion};
+use crate::interpreter::Interpreter;
+use crate::parser::span::Span;
+use crate::repl::Context;
+
+/// Collect the suggestions of a diagnostic, along with the lines they apply to
+fn collect_edits(
+    diagnostic: &Diagnostic,
+    location: Location,
+    edits: &mut Vec<(Location, Suggestion)>,
+) {
+    for suggestion in &diagnostic.suggestions {
+        edits.push((suggestion.location.unwrap_or(location), suggestion.clone()));
+    }
+}
+
+/// Apply all suggestions for the errors and warnings (that aren't allowed) in a program.
+/// Returns the fixed program and the number of suggestions that were applied.
+pub fn fix(source: &str, lint_levels: LintLevels) -> (String, usize) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_check_only(true);
+    interpreter.set_lint_levels(lint_levels);
+    let source_id = interpreter.add_source(Context::Repl);
+
+    let mut edits = vec![];
+    let mut last_location = None;
+    for line in source.lines() {
+        // Spans refer to the line with tabs replaced, like when it is executed
+        let location = interpreter.push_line(source_id, line);
+        let line = expand_tabs(line);
+        last_location = Some(location);
+
+        let mut warnings = vec![];
+        if let Err(error) = interpreter.execute(&line, location, &mut warnings) {
+            collect_edits(&error, location, &mut edits);
+        }
+        for warning in &warnings {
+            if let Some(diagnostic) = interpreter.lint(warning, &line) {
+                collect_edits(&diagnostic, location, &mut edits);
+            }
+        }
+    }
+
+    let mut warnings = vec![];
+    interpreter.finish(&mut warnings);
+    if let Some(location) = last_location {
+        for warning in &warnings {
+            if let Some(diagnostic) = interpreter.lint(warning, "") {
+                collect_edits(&diagnostic, location, &mut edits);
+            }
+        }
+    }
+
+    // Apply edits from the back of each line, so the spans of earlier ones stay valid
+    edits.sort_by_key(|(location, suggestion)| (*location, std::cmp::Reverse(suggestion.span.0)));
+    let mut lines: Vec<String> = source.lines().map(str::to_owned).collect();
+    let mut applied = 0;
+    let mut previous: Option<&(Location, Suggestion)> = None;
+    for edit in &edits {
+        let (location, suggestion) = edit;
+        let same_line = previous.filter(|(previous, _)| previous == location);
+
+        // Overlapping edits can't both be applied
+        if same_line.is_some_and(|(_, previous)| suggestion.span.1 > previous.span.0) {
+            continue;
+        }
+
+        // Edits are applied to the original line, so its tabs are kept
+        let line = &mut lines[location.line - 1];
+        let span = Span(
+            original_position(line, suggestion.span.0),
+            original_position(line, suggestion.span.1),
+        );
+        *line = Suggestion {
+            span,
+            ..suggestion.clone()
+        }
+        .apply(line);
+
+        applied += 1;
+        previous = Some(edit);
+    }
+
+    let mut fixed = lines.join("\n");
+    if source.ends_with('\n') {
+        fixed.push('\n');
+    }
+    (fixed, applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::lint::Level;
+
+    #[test]
+    fn redundant_true() {
+        let (fixed, applied) = fix("true => a(x) and true\n", LintLevels::default());
+        assert_eq!(fixed, "true => a(x)\n");
+        assert_eq!(applied, 1);
+    }
+
+    #[test]
+    fn allowed_lints_are_not_fixed() {
+        let mut lint_levels = LintLevels::default();
+        lint_levels.set("redundant_true", Level::Allow);
+        let source = "true => a(x) and true\n";
+        assert_eq!(fix(source, lint_levels), (source.to_owned(), 0));
+    }
+
+    #[test]
+    fn tabs_are_kept() {
+        let source = "forall X, X\n\tbird(X) and true => canfly(X)\t// comment\n";
+        let (fixed, applied) = fix(source, LintLevels::default());
+        assert_eq!(fixed, "forall X\n\tbird(X) => canfly(X)\t// comment\n");
+        assert_eq!(applied, 2);
+
+        let (fixed, applied) = fix("\t\ttrue => a(x)", LintLevels::default());
+        assert_eq!(fixed, "true => a(x)");
+        assert_eq!(applied, 1);
+    }
+}
@@ -0,0 +1,227 @@
+//! Pretty-prints Leuchtkraft programs in a canonical style:
+//!
+//! * Rules inside `forall` blocks are indented with four spaces
+//! * `and`, `=>` and commas are surrounded by single spaces
+//! * Trailing comments on consecutive lines are aligned
+//! * Consecutive empty lines are collapsed into one
+
+use crate::diagnostics::Diagnostic;
+use crate::parser::parser::Parser;
+use crate::parser::span::Spanned;
+use crate::parser::symbol::{Atom, Line, Statement};
+
+const INDENT: &str = "    ";
+
+/// A formatted line, before trailing comments are aligned
+struct FormattedLine {
+    code: String,
+    comment: Option<String>,
+}
+
+fn format_atom(atom: &Atom) -> String {
+    match atom {
+        Atom::True => "true".to_owned(),
+        Atom::False => "false".to_owned(),
+        Atom::Predicate(name, args) => {
+            let args: Vec<&str> = args.iter().map(|arg| *arg.as_inner()).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Atom::Unknown(name) => format!("{}?", name),
+    }
+}
+
+fn format_line(line: &Line) -> String {
+    match line {
+        Line::Forall(idents) => {
+            let idents: Vec<&str> = idents.iter().map(|ident| *ident.as_inner()).collect();
+            format!("forall {}", idents.join(", "))
+        }
+        Line::Import(path) => format!("import \"{}\"", path.as_inner()),
+        Line::Module(name) => format!("module {}", name.as_inner()),
+        Line::Use(name) => format!("use {}", name.as_inner()),
+        Line::Rule(is_indented, _, and_chains) => {
+            let and_chains: Vec<String> = and_chains
+                .iter()
+                .map(|and_chain| {
+                    and_chain
+                        .iter()
+                        .map(|atom| format_atom(atom.as_inner()))
+                        .collect::<Vec<String>>()
+                        .join(" and ")
+                })
+                .collect();
+            let indent = if *is_indented { INDENT } else { "" };
+            format!("{}{}", indent, and_chains.join(" => "))
+        }
+    }
+}
+
+fn format_comment(comment: &Spanned<&str>) -> String {
+    let text = comment.as_inner().trim_end();
+    if text.is_empty() || text.starts_with(' ') {
+        format!("//{}", text)
+    } else {
+        format!("// {}", text)
+    }
+}
+
+/// Format a Leuchtkraft program.
+/// If the program contains a syntax error, the line number and the
+/// corresponding diagnostic are returned instead.
+// Diagnostics are only created once per line, so their size doesn't matter
+#[allow(clippy::result_large_err)]
+pub fn format(source: &str) -> Result<String, (usize, Diagnostic<'_>)> {
+    let mut lines: Vec<Option<FormattedLine>> = vec![];
+    let mut inside_scopeblock = false;
+
+    for (ix, line) in source.lines().enumerate() {
+        // A `#!` line is kept as it is
+        if ix == 0 && line.starts_with("#!") {
+            lines.push(Some(FormattedLine {
+                code: line.trim_end().to_owned(),
+                comment: None,
+            }));
+            continue;
+        }
+
+        let mut warnings = vec![];
+        let Statement {
+            line: parsed_line,
+            comment,
+        } = Parser::new(line)
+            .line(&mut warnings)
+            .map_err(|err| (ix + 1, Diagnostic::from((err, line))))?;
+        let comment = comment.as_ref().map(format_comment);
+
+        let code = match &parsed_line {
+            Some(parsed_line) => {
+                match parsed_line.as_inner() {
+                    Line::Forall(_) => inside_scopeblock = true,
+                    Line::Rule(false, ..) | Line::Import(_) | Line::Module(_) | Line::Use(_) => {
+                        inside_scopeblock = false
+                    }
+                    Line::Rule(true, ..) => {}
+                }
+                format_line(parsed_line.as_inner())
+            }
+            None => {
+                if comment.is_none() {
+                    // Empty line
+                    lines.push(None);
+                    continue;
+                }
+
+                // Comments inside forall blocks keep their indentation
+                let is_indented = line.starts_with(char::is_whitespace);
+                if inside_scopeblock && is_indented {
+                    INDENT.to_owned()
+                } else {
+                    String::new()
+                }
+            }
+        };
+        lines.push(Some(FormattedLine { code, comment }));
+    }
+
+    // Only keep a single empty line between blocks of code
+    let mut collapsed: Vec<Option<FormattedLine>> = vec![];
+    for line in lines {
+        if line.is_some() || collapsed.last().is_some_and(|last| last.is_some()) {
+            collapsed.push(line);
+        }
+    }
+    while collapsed.last().is_some_and(|last| last.is_none()) {
+        collapsed.pop();
+    }
+
+    let mut formatted = String::new();
+    let mut ix = 0;
+    while ix < collapsed.len() {
+        // Find the group of consecutive lines with code and trailing comments
+        let group_len = collapsed[ix..]
+            .iter()
+            .take_while(|line| {
+                line.as_ref()
+                    .is_some_and(|line| line.comment.is_some() && !line.code.trim().is_empty())
+            })
+            .count();
+
+        if group_len == 0 {
+            if let Some(line) = &collapsed[ix] {
+                formatted.push_str(&line.code);
+                if let Some(comment) = &line.comment {
+                    formatted.push_str(comment);
+                }
+            }
+            formatted.push('\n');
+            ix += 1;
+            continue;
+        }
+
+        let group = &collapsed[ix..ix + group_len];
+        let column = group
+            .iter()
+            .flatten()
+            .map(|line| line.code.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for line in group.iter().flatten() {
+            formatted.push_str(&format!(
+                "{:width$}{}\n",
+                line.code,
+                line.comment.as_deref().unwrap_or(""),
+                width = column
+            ));
+        }
+        ix += group_len;
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(source: &str) -> String {
+        match format(source) {
+            Ok(formatted) => formatted,
+            Err((lineno, _)) => panic!("syntax error in line {}", lineno),
+        }
+    }
+
+    #[test]
+    fn spacing() {
+        let source = "forall x,y\n  a(x)and b(y)=>c(x,y)\n";
+        assert_eq!(
+            formatted(source),
+            "forall x, y\n    a(x) and b(y) => c(x, y)\n"
+        );
+    }
+
+    #[test]
+    fn empty_lines() {
+        let source = "\n\ntrue => a(x)\n\n\n\ntrue => b(x)\n\n";
+        assert_eq!(formatted(source), "true => a(x)\n\ntrue => b(x)\n");
+    }
+
+    #[test]
+    fn aligned_comments() {
+        let source = "true => a(x) //first\ntrue => länger(x) // second\n";
+        assert_eq!(
+            formatted(source),
+            "true => a(x)      // first\ntrue => länger(x) // second\n"
+        );
+    }
+
+    #[test]
+    fn shebang() {
+        let source = "#!/usr/bin/env leuchtkraft\ntrue => a(x)\n";
+        assert_eq!(formatted(source), source);
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert!(matches!(format("true => a(x)\ntrue => b(x\n"), Err((2, _))));
+    }
+}
@@ -1,16 +1,73 @@
 use crate::debug::error::Error;
+use crate::debug::lint::{LintLevels, Linter};
+use crate::debug::semantic::SemanticLinter;
 use crate::debug::warning::Warning;
-use crate::diagnostics::Diagnostic;
-use crate::logic::{Atom, Clause, Ident, LogicEngine, Var};
+use crate::diagnostics::{Diagnostic, Location, SourceId, SourceMap};
+use crate::logic::{
+    Atom, CancellationToken, Clause, Ident, Limit, Limits, LogicEngine, UnknownValue, Var,
+};
 use crate::parser::parser::Parser;
 use crate::parser::span::{Span, Spanned};
 use crate::parser::symbol::{Atom as AtomSymbol, Line};
-use crate::util::calculate_hash;
+use crate::repl::Context;
+use crate::symbol_table::{str_to_ident, SymbolTable};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+/// How questions with more than one unknown are answered
+pub enum AnswerMode {
+    /// Every unknown is resolved on its own
+    Independent,
+    /// List the combinations of values for all unknowns that are consistent
+    /// with the known clauses
+    Joint,
+}
 
 pub struct Interpreter {
     inside_scopeblock: bool,
-    free_vars: Vec<Ident>,
+    free_vars: Vec<Spanned<Ident>>,
+    /// The most recent forall statement
+    forall_location: Option<Location>,
     logic_engine: LogicEngine,
+    symbols: SymbolTable,
+    /// The most recently asked question, used for model enumeration
+    last_question: Option<Clause<Ident>>,
+    /// The values of the unknowns in the most recently answered question
+    last_answer: Option<Vec<(String, UnknownValue)>>,
+    answer_mode: AnswerMode,
+    /// Only check questions for errors instead of answering them
+    check_only: bool,
+    /// Whether any question could not be answered because the known
+    /// clauses contradict each other
+    found_contradiction: bool,
+    linter: Linter,
+    semantic_linter: SemanticLinter,
+    /// All code that was executed so far
+    sources: SourceMap,
+    /// Lines containing clauses, forall, import, module and use statements that were accepted
+    accepted: Vec<String>,
+    /// The path of the most recent import statement, until the caller loads it
+    import: Option<Spanned<String>>,
+    /// Canonical paths of the files that are currently being executed, innermost last
+    loading: Vec<PathBuf>,
+    /// Canonical paths of all files that were executed completely
+    loaded: HashSet<PathBuf>,
+    /// How many of the files that are being executed were imported, rather than loaded by the user
+    importing: usize,
+    /// All modules that were declared so far
+    modules: HashSet<String>,
+    /// The module statements of every source that contains any
+    scopes: HashMap<SourceId, Scope>,
+}
+
+/// The module statements of a source
+#[derive(Default)]
+struct Scope {
+    /// The module declared by a `module` statement, `None` for the global namespace
+    module: Option<String>,
+    /// Modules brought into scope with `use` statements
+    uses: Vec<String>,
 }
 
 impl Interpreter {
@@ -18,7 +75,406 @@ impl Interpreter {
         Self {
             inside_scopeblock: false,
             free_vars: vec![],
+            forall_location: None,
             logic_engine: LogicEngine::default(),
+            symbols: SymbolTable::default(),
+            last_question: None,
+            last_answer: None,
+            answer_mode: AnswerMode::Independent,
+            check_only: false,
+            found_contradiction: false,
+            linter: Linter::default(),
+            semantic_linter: SemanticLinter::default(),
+            sources: SourceMap::default(),
+            accepted: vec![],
+            import: None,
+            loading: vec![],
+            importing: 0,
+            loaded: HashSet::new(),
+            modules: HashSet::new(),
+            scopes: HashMap::new(),
+        }
+    }
+
+    /// Forget all known clauses, as if the session was just started.
+    /// Settings like the lint levels and resource limits are kept.
+    pub fn reset(&mut self) {
+        self.logic_engine.clear();
+        // Forgotten predicates shouldn't be completed or make names ambiguous
+        self.symbols = SymbolTable::default();
+        self.inside_scopeblock = false;
+        self.free_vars.clear();
+        self.forall_location = None;
+        self.last_question = None;
+        self.last_answer = None;
+        self.found_contradiction = false;
+        self.semantic_linter = SemanticLinter::default();
+        self.accepted.clear();
+        self.import = None;
+        self.loaded.clear();
+        self.modules.clear();
+        self.scopes.clear();
+    }
+
+    /// The lines containing clauses and statements that were executed
+    /// without errors, in order. Questions are not included.
+    /// Imported files are represented by their import statement.
+    pub fn accepted_lines(&self) -> &[String] {
+        &self.accepted
+    }
+
+    /// Remember a line that was executed without errors, unless it is part of an imported file.
+    fn accept(&mut self, line: &str) {
+        if self.importing == 0 {
+            self.accepted.push(line.to_owned());
+        }
+    }
+
+    /// Format the known clauses with their original names.
+    /// If a predicate is given, only the clauses mentioning it are included.
+    pub fn list_clauses(&self, predicate: Option<&str>) -> Vec<String> {
+        let predicate = predicate.map(str_to_ident);
+        self.logic_engine
+            .clauses()
+            .iter()
+            .filter(|clause| match predicate {
+                Some(predicate) => {
+                    clause.0.iter().flatten().any(
+                        |atom| matches!(atom, Atom::Predicate(ident, _) if *ident == predicate),
+                    )
+                }
+                None => true,
+            })
+            .map(|clause| self.symbols.display_clause(clause))
+            .collect()
+    }
+
+    /// The names that were used so far, for completing them in the repl
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Whether the previous lines opened a forall block that is still going on
+    pub fn inside_scopeblock(&self) -> bool {
+        self.inside_scopeblock
+    }
+
+    /// The variables freed by the current forall block, along with the
+    /// location of its forall statement
+    pub fn scope(&self) -> Option<(Vec<String>, Location)> {
+        if !self.inside_scopeblock {
+            return None;
+        }
+        let names = self
+            .free_vars
+            .iter()
+            .map(|var| self.symbols.name(*var.as_inner()))
+            .collect();
+        Some((names, self.forall_location?))
+    }
+
+    /// End the current forall block (if any) without waiting for an unindented line
+    pub fn close_block(&mut self, warnings: &mut Vec<Warning>) {
+        if self.inside_scopeblock {
+            self.inside_scopeblock = false;
+            self.semantic_linter.close_block(warnings);
+        }
+    }
+
+    /// Register a new source of code, like a file or a repl session
+    pub fn add_source(&mut self, context: Context) -> SourceId {
+        self.sources.add(context)
+    }
+
+    /// Remember a line of code before executing it, so that later
+    /// diagnostics can refer to it
+    pub fn push_line(&mut self, source: SourceId, line: &str) -> Location {
+        self.sources.push_line(source, line)
+    }
+
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.logic_engine.set_limits(limits);
+    }
+
+    /// Get a token that can be used to cancel the question that is currently being answered
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.logic_engine.cancellation_token()
+    }
+
+    pub fn set_answer_mode(&mut self, answer_mode: AnswerMode) {
+        self.answer_mode = answer_mode;
+    }
+
+    pub fn answer_mode(&self) -> AnswerMode {
+        self.answer_mode
+    }
+
+    /// When enabled, questions are still checked for errors but never resolved
+    pub fn set_check_only(&mut self, check_only: bool) {
+        self.check_only = check_only;
+    }
+
+    pub fn is_check_only(&self) -> bool {
+        self.check_only
+    }
+
+    pub fn set_lint_levels(&mut self, levels: LintLevels) {
+        self.linter = Linter::new(levels);
+    }
+
+    /// Create a diagnostic for a warning on the most recently executed line,
+    /// according to its lint level.
+    /// Returns `None` if the warning is allowed.
+    pub fn lint<'a>(&self, warning: &'a Warning, line: &'a str) -> Option<Diagnostic<'a>> {
+        self.linter.diagnostic(warning, line)
+    }
+
+    /// Resolve every unknown in the question on its own
+    fn answer(&mut self, question: Clause<Ident>) -> String {
+        let unknowns = question.unknowns();
+        let resolvents = self.logic_engine.resolve(question);
+        self.found_contradiction |= resolvents.contains(&UnknownValue::Neither);
+        let mut answer = format!("resolved to {:?}", resolvents);
+        if let Some(limit) = resolvents.iter().find_map(|value| match value {
+            UnknownValue::LimitExceeded(limit) => Some(limit),
+            _ => None,
+        }) {
+            answer.push_str(&format!(" (resource limit exceeded: {})", limit));
+        }
+
+        self.last_answer = Some(
+            unknowns
+                .iter()
+                .map(|unknown| self.symbols.name(*unknown))
+                .zip(resolvents)
+                .collect(),
+        );
+        answer
+    }
+
+    /// Whether any question so far resolved to `Neither`, meaning that
+    /// the known clauses contradict each other
+    pub fn found_contradiction(&self) -> bool {
+        self.found_contradiction
+    }
+
+    /// Take the names and values of the unknowns in the most recently
+    /// answered question (if it was answered independently)
+    pub fn take_answer(&mut self) -> Option<Vec<(String, UnknownValue)>> {
+        self.last_answer.take()
+    }
+
+    /// Take the path of the most recently executed import statement.
+    /// Importing the file is up to the caller, since the interpreter doesn't read files.
+    pub fn take_import(&mut self) -> Option<Spanned<String>> {
+        self.import.take()
+    }
+
+    /// Remember that a file is being executed, until [Interpreter::leave_file] is called
+    pub fn enter_file(&mut self, path: PathBuf) {
+        self.loading.push(path);
+    }
+
+    /// Mark the innermost file that is being executed as completely executed
+    pub fn leave_file(&mut self) {
+        if let Some(path) = self.loading.pop() {
+            self.loaded.insert(path);
+        }
+    }
+
+    /// Remember that the lines up to [Interpreter::leave_import] come from an imported file
+    pub fn enter_import(&mut self) {
+        self.importing += 1;
+    }
+
+    /// Mark the innermost imported file as completely executed
+    pub fn leave_import(&mut self) {
+        self.importing = self.importing.saturating_sub(1);
+    }
+
+    /// Whether a file is currently being executed, which makes importing it a cycle
+    pub fn is_loading(&self, path: &Path) -> bool {
+        self.loading.iter().any(|loading| loading == path)
+    }
+
+    /// Whether a file was already executed completely
+    pub fn is_loaded(&self, path: &Path) -> bool {
+        self.loaded.contains(path)
+    }
+
+    /// List the consistent combinations of values for the unknowns in the question
+    fn answer_jointly(&mut self, question: Clause<Ident>) -> String {
+        let unknowns = question.unknowns();
+        match self.logic_engine.resolve_jointly(question) {
+            Ok(combinations) if combinations.is_empty() => {
+                self.found_contradiction = true;
+                "resolved jointly to Neither".to_owned()
+            }
+            Ok(combinations) => {
+                let combinations: Vec<String> = combinations
+                    .iter()
+                    .map(|values| {
+                        let assignments: Vec<String> = unknowns
+                            .iter()
+                            .zip(values)
+                            .map(|(unknown, value)| {
+                                let value = if *value {
+                                    UnknownValue::True
+                                } else {
+                                    UnknownValue::False
+                                };
+                                format!("{}? = {:?}", self.symbols.name(*unknown), value)
+                            })
+                            .collect();
+                        format!("[{}]", assignments.join(", "))
+                    })
+                    .collect();
+                format!("resolved jointly to {}", combinations.join(" or "))
+            }
+            Err(limit) => format!(
+                "resolved jointly to {:?} (resource limit exceeded: {})",
+                UnknownValue::LimitExceeded(limit),
+                limit
+            ),
+        }
+    }
+
+    /// Remember the names of all predicates and arguments in a clause
+    fn intern_names(&mut self, and_chains: &[Vec<Spanned<AtomSymbol>>]) {
+        for atom in and_chains.iter().flatten() {
+            match atom.as_inner() {
+                AtomSymbol::Predicate(name, args) => {
+                    self.symbols.intern_predicate(name, args.len());
+                    for arg in args {
+                        let ident = str_to_ident(arg.as_inner());
+                        if self.free_vars.iter().any(|var| *var.as_inner() == ident) {
+                            self.symbols.intern(arg.as_inner());
+                        } else {
+                            self.symbols.intern_constant(arg.as_inner());
+                        }
+                    }
+                }
+                AtomSymbol::Unknown(name) => {
+                    self.symbols.intern(name);
+                }
+                AtomSymbol::True | AtomSymbol::False => {}
+            }
+        }
+    }
+
+    /// Derive every fact that follows from the known clauses and format
+    /// them as a Leuchtkraft program.
+    /// Returns `None` if the known clauses contradict each other.
+    pub fn materialize(&self) -> Result<Option<String>, Limit> {
+        let model = self.logic_engine.materialize()?;
+        Ok(model.map(|model| self.symbols.display_model(&model)))
+    }
+
+    /// Find up to `limit` consistent worlds for the most recently asked question.
+    /// Only the atoms whose values differ between the worlds are listed.
+    /// If `projection` is not empty, only the given predicates are considered.
+    /// Fails if there is no question or no world consistent with it.
+    pub fn models(&self, limit: usize, projection: &[&str]) -> Result<Vec<String>, String> {
+        let question = match &self.last_question {
+            Some(question) => question.clone(),
+            None => return Err("No question has been asked yet".to_owned()),
+        };
+        let projection: Vec<Ident> = projection.iter().map(|name| str_to_ident(name)).collect();
+
+        let models = match self
+            .logic_engine
+            .enumerate_models(question, limit, &projection)
+        {
+            Ok(models) => models,
+            Err(limit) => {
+                return Err(format!(
+                    "Could not enumerate the worlds (resource limit exceeded: {})",
+                    limit
+                ))
+            }
+        };
+        if models.is_empty() {
+            return Err("No world is consistent with the last question".to_owned());
+        }
+
+        // Atoms that have the same value in every world are not interesting
+        let differs = |atom: &Atom<Ident>, value: bool| {
+            models.len() == 1
+                || models.iter().any(|model| {
+                    model
+                        .0
+                        .iter()
+                        .any(|(other, other_value)| other == atom && *other_value != value)
+                })
+        };
+
+        Ok(models
+            .iter()
+            .enumerate()
+            .map(|(ix, model)| {
+                let assignments: Vec<String> = model
+                    .0
+                    .iter()
+                    .filter(|(atom, value)| differs(atom, *value))
+                    .map(|(atom, value)| {
+                        format!("{} = {}", self.symbols.display_ground_atom(atom), value)
+                    })
+                    .collect();
+                format!("world {}: {}", ix + 1, assignments.join(", "))
+            })
+            .collect())
+    }
+
+    /// Find the predicate a name refers to, qualified with its module (if any).
+    /// Unqualified names refer to a predicate in the module of the source (or the
+    /// global namespace) or in one of the modules it uses. New predicates are
+    /// always added to the module of the source.
+    fn resolve(&self, name: &str, span: Span, source: SourceId) -> Result<String, Error> {
+        let scope = self.scopes.get(&source);
+        let module = scope.and_then(|scope| scope.module.as_deref());
+
+        if let Some((qualifier, predicate)) = name.split_once('.') {
+            if !self.modules.contains(qualifier) {
+                return Err(Error::UnknownModule {
+                    span: Span(span.0, span.0 + qualifier.chars().count()),
+                    module: qualifier.to_owned(),
+                });
+            }
+            if module != Some(qualifier) && !self.symbols.is_predicate(name) {
+                return Err(Error::UnknownPredicate {
+                    span,
+                    module: qualifier.to_owned(),
+                    predicate: predicate.to_owned(),
+                });
+            }
+            return Ok(name.to_owned());
+        }
+
+        let local = match module {
+            Some(module) => format!("{}.{}", module, name),
+            None => name.to_owned(),
+        };
+        let mut candidates: Vec<String> = scope
+            .into_iter()
+            .flat_map(|scope| &scope.uses)
+            .map(|used| format!("{}.{}", used, name))
+            .filter(|qualified| *qualified != local && self.symbols.is_predicate(qualified))
+            .collect();
+        if self.symbols.is_predicate(&local) {
+            candidates.insert(0, local.clone());
+        }
+        match candidates.len() {
+            0 => Ok(local),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(Error::AmbiguousName {
+                span,
+                name: name.to_owned(),
+                candidates,
+            }),
         }
     }
 
@@ -42,19 +498,26 @@ impl Interpreter {
                             AtomSymbol::Predicate(name, args) => {
                                 // Check if the args were freed using a forall statement
                                 let checked_args: Result<Vec<T>, Error> = args
-                                    .into_iter()
+                                    .iter()
                                     .map(|ident_str| {
                                         ident_str.map(str_to_ident(ident_str.as_inner()))
                                     })
                                     .map(|arg| {
                                         let span = arg.span();
-                                        let var = if self.free_vars.contains(arg.as_inner()) {
-                                            Var::Free(arg.into_inner())
-                                        } else {
-                                            Var::Fixed(arg.into_inner())
+                                        let declaration = self
+                                            .free_vars
+                                            .iter()
+                                            .find(|var| var.as_inner() == arg.as_inner());
+                                        let var = match declaration {
+                                            Some(_) => Var::Free(arg.into_inner()),
+                                            None => Var::Fixed(arg.into_inner()),
                                         };
-                                        T::try_from(var)
-                                            .map_err(|_| Error::FreedVarInQuestion { span: span })
+                                        T::try_from(var).map_err(|_| Error::FreedVarInQuestion {
+                                            span,
+                                            declaration: declaration
+                                                .zip(self.forall_location)
+                                                .map(|(var, location)| (location, var.span())),
+                                        })
                                     })
                                     .collect();
                                 Ok(Atom::Predicate(str_to_ident(name), checked_args?))
@@ -69,47 +532,126 @@ impl Interpreter {
         clause_raw.map(|and_chain| Clause::new(and_chain))
     }
 
+    /// Run any checks that can only be done once the program is complete
+    pub fn finish(&mut self, warnings: &mut Vec<Warning>) {
+        // A forall block can't continue in the next file
+        self.inside_scopeblock = false;
+        self.semantic_linter.close_block(warnings);
+    }
+
+    // Diagnostics are only created once per line, so their size doesn't matter
+    #[allow(clippy::result_large_err)]
     pub fn execute<'a>(
         &mut self,
         line: &'a str,
+        location: Location,
         warnings: &mut Vec<Warning>,
     ) -> Result<Option<String>, Diagnostic<'a>> {
         // Parse the line
-        let parser = Parser::new(line);
-        let line_content = parser
+        let parser = if location.line == 1 {
+            Parser::first_line(line)
+        } else {
+            Parser::new(line)
+        };
+        let statement = parser
             .line(warnings)
             .map_err(|err| Diagnostic::from((err, line)))?;
-        if let Some(parsed_line) = line_content {
+        self.linter.enter_line(
+            statement.line.is_some(),
+            statement.comment.as_ref(),
+            warnings,
+        );
+        if let Some(parsed_line) = statement.line {
             let line_span = parsed_line.span();
             match parsed_line.into_inner() {
                 Line::Forall(free_vars) => {
+                    self.semantic_linter
+                        .enter_forall(location, &free_vars, warnings);
                     self.inside_scopeblock = true;
+                    self.forall_location = Some(location);
                     self.free_vars = free_vars
                         .into_iter()
-                        .map(|spanned| spanned.into_inner())
-                        .map(|ident_str| str_to_ident(ident_str))
+                        .map(|spanned| {
+                            let ident = self.symbols.intern(spanned.as_inner());
+                            spanned.map(ident)
+                        })
                         .collect();
+                    self.accept(line);
+                }
+                Line::Import(path) => {
+                    // Imports end forall blocks just like unindented rules
+                    self.close_block(warnings);
+                    self.import = Some(path.map((*path.as_inner()).to_owned()));
+                    self.accept(line);
+                }
+                Line::Module(name) => {
+                    self.close_block(warnings);
+                    self.modules.insert(name.as_inner().to_string());
+                    self.scopes.entry(location.source).or_default().module =
+                        Some(name.as_inner().to_string());
+                    self.accept(line);
+                }
+                Line::Use(name) => {
+                    self.close_block(warnings);
+                    if !self.modules.contains(*name.as_inner()) {
+                        let error = Error::UnknownModule {
+                            span: name.span(),
+                            module: name.as_inner().to_string(),
+                        };
+                        return Err(Diagnostic::from((error, line)));
+                    }
+                    let uses = &mut self.scopes.entry(location.source).or_default().uses;
+                    if !uses.iter().any(|used| used == name.as_inner()) {
+                        uses.push(name.as_inner().to_string());
+                    }
+                    self.accept(line);
                 }
                 Line::Rule(is_indented, is_question, and_chains) => {
                     // Check indentation level
                     match (self.inside_scopeblock, is_indented) {
-                        (true, false) => self.inside_scopeblock = false,
+                        (true, false) => self.close_block(warnings),
                         (false, true) => {
                             return Err(Diagnostic::from((Error::UnexpectedIndent, line)));
                         }
                         _ => {}
                     }
 
+                    // Refer to predicates by their qualified names from now on
+                    let names: Vec<String> = and_chains
+                        .iter()
+                        .flatten()
+                        .filter_map(|atom| match atom.as_inner() {
+                            AtomSymbol::Predicate(name, _) => Some(self.resolve(
+                                name,
+                                Span(atom.span().0, atom.span().0 + name.chars().count()),
+                                location.source,
+                            )),
+                            _ => None,
+                        })
+                        .collect::<Result<_, _>>()
+                        .map_err(|err| Diagnostic::from((err, line)))?;
+                    let and_chains = qualify(and_chains, &names);
+
                     // Run some general checks on the clause
                     // (these checks only throw warnings, no errors)
                     sanity_check_clause(&and_chains, line_span, warnings);
+                    self.semantic_linter
+                        .check_rule(&and_chains, is_question, location, warnings);
+                    self.intern_names(&and_chains);
 
                     if is_question {
                         let question: Clause<Ident> = self
                             .symbol_to_clause(and_chains)
                             .map_err(|err| Diagnostic::from((err, line)))?;
-                        let resolvents = self.logic_engine.resolve(question);
-                        return Ok(Some(format!("resolved to {:?}", resolvents)));
+                        if self.check_only {
+                            return Ok(None);
+                        }
+                        self.last_question = Some(question.clone());
+                        let answer = match self.answer_mode {
+                            AnswerMode::Independent => self.answer(question),
+                            AnswerMode::Joint => self.answer_jointly(question),
+                        };
+                        return Ok(Some(answer));
                     } else {
                         // Can never fail but lets handle the error
                         // anyways, for clarity
@@ -117,6 +659,7 @@ impl Interpreter {
                             .symbol_to_clause(and_chains)
                             .map_err(|err| Diagnostic::from((err, line)))?;
                         self.logic_engine.add(clause);
+                        self.accept(line);
                     }
                 }
             }
@@ -125,8 +668,30 @@ impl Interpreter {
     }
 }
 
-fn str_to_ident(ident_str: &str) -> Ident {
-    Ident(calculate_hash(&ident_str))
+/// Replace the names of the predicates with the given names, in order
+fn qualify<'a>(
+    and_chains: Vec<Vec<Spanned<AtomSymbol<'a>>>>,
+    names: &'a [String],
+) -> Vec<Vec<Spanned<AtomSymbol<'a>>>> {
+    let mut names = names.iter();
+    and_chains
+        .into_iter()
+        .map(|and_chain| {
+            and_chain
+                .into_iter()
+                .map(|atom| {
+                    let span = atom.span();
+                    match atom.into_inner() {
+                        AtomSymbol::Predicate(_, args) => {
+                            let name = names.next().expect("every predicate has a name");
+                            Spanned::new(AtomSymbol::Predicate(name, args), span)
+                        }
+                        other => Spanned::new(other, span),
+                    }
+                })
+                .collect()
+        })
+        .collect()
 }
 
 fn sanity_check_clause(
@@ -138,13 +703,20 @@ fn sanity_check_clause(
     let mut contains_non_literal = false;
     atoms.iter().enumerate().for_each(|(block_ix, and_chain)| {
         // Check for redundant trues (x and true => y)
-        if let Some(true_symbol) = and_chain
+        if let Some(ix) = and_chain
             .iter()
-            .find(|atom| atom.as_inner() == &AtomSymbol::True)
+            .position(|atom| atom.as_inner() == &AtomSymbol::True)
         {
             if and_chain.len() != 1 {
+                let true_symbol = &and_chain[ix];
+                // Remove the 'and' before the 'true' (or after it, if it comes first)
+                let removal = match ix {
+                    0 => Span(true_symbol.span().0, and_chain[1].span().0),
+                    _ => Span(and_chain[ix - 1].span().1, true_symbol.span().1),
+                };
                 warnings.push(Warning::RedundantTrue {
                     span: true_symbol.span(),
+                    removal,
                 });
             }
         }
@@ -174,3 +746,164 @@ fn sanity_check_clause(
         warnings.push(Warning::PurelyLiteralClause { span: clause_span });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Execute the lines of a source that are expected to contain no errors
+    fn execute(i: &mut Interpreter, name: &str, program: &str) -> SourceId {
+        let source = i.add_source(Context::File(PathBuf::from(name)));
+        for line in program.lines() {
+            let location = i.push_line(source, line);
+            assert!(i.execute(line, location, &mut vec![]).is_ok(), "{}", line);
+        }
+        source
+    }
+
+    /// Execute a program that is expected to contain no errors
+    fn interpreter(program: &str, limits: Limits) -> Interpreter {
+        let mut i = Interpreter::new();
+        i.set_limits(limits);
+        execute(&mut i, "test.le", program);
+        i
+    }
+
+    #[test]
+    fn materialize_within_max_atoms() {
+        // A single clause with 10^4 instances
+        let mut program: String = (0..10).map(|n| format!("true => c(k{})\n", n)).collect();
+        program
+            .push_str("forall A, B, C, D\n    c(A) and c(B) and c(C) and c(D) => d(A, B, C, D)\n");
+        let limits = Limits {
+            max_atoms: Some(100),
+            ..Limits::default()
+        };
+        let i = interpreter(&program, limits);
+        assert_eq!(i.materialize().err(), Some(Limit::Atoms));
+    }
+
+    #[test]
+    fn materialize_step_limit() {
+        // The chain is written backwards, so every clause takes another propagation step
+        let program: String = (0..10)
+            .rev()
+            .map(|n| format!("a{}(x) => a{}(x)\n", n, n + 1))
+            .chain(["true => a0(x)".to_owned()])
+            .collect();
+        let limits = Limits {
+            max_steps: Some(3),
+            ..Limits::default()
+        };
+        let i = interpreter(&program, limits);
+        assert_eq!(i.materialize().err(), Some(Limit::Steps));
+
+        let i = interpreter(&program, Limits::default());
+        assert!(i.materialize().unwrap().unwrap().contains("true => a10(x)"));
+    }
+
+    #[test]
+    fn materialize() {
+        let i = interpreter("true => a(x)", Limits::default());
+        assert_eq!(i.materialize().unwrap().as_deref(), Some("true => a(x)\n"));
+    }
+
+    #[test]
+    fn models_without_question() {
+        let i = interpreter("true => a(x)", Limits::default());
+        assert!(i.models(2, &[]).is_err());
+    }
+
+    #[test]
+    fn reset() {
+        let mut i = interpreter("module geo\ntrue => inside(x)", Limits::default());
+        i.reset();
+        assert!(i.list_clauses(None).is_empty());
+        assert_eq!(i.symbols().predicates().count(), 0);
+        assert_eq!(i.symbols().constants().count(), 0);
+    }
+
+    #[test]
+    fn models_with_many_unknowns() {
+        // More unknowns than there are bits in an usize
+        let unknowns: Vec<String> = (0..70).map(|n| format!("u{}?", n)).collect();
+        let program = format!("true => a(x)\n{} => a(x)", unknowns.join(" and "));
+        let i = interpreter(&program, Limits::default());
+        assert_eq!(
+            i.models(3, &[]).unwrap(),
+            [
+                "world 1: u0? = true, u1? = true",
+                "world 2: u0? = false, u1? = true",
+                "world 3: u0? = true, u1? = false",
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_unqualified_names() {
+        let mut i = Interpreter::new();
+        execute(&mut i, "geo.le", "module geo\ntrue => inside(x)");
+        let main = execute(&mut i, "main.le", "use geo\ntrue => outside(x)");
+        let other = execute(&mut i, "other.le", "");
+
+        let resolve = |name, source| i.resolve(name, Span(0, 0), source).ok();
+        assert_eq!(resolve("inside", main).as_deref(), Some("geo.inside"));
+        assert_eq!(resolve("outside", main).as_deref(), Some("outside"));
+        // New predicates belong to the module of the source
+        assert_eq!(resolve("new", main).as_deref(), Some("new"));
+        // Modules have to be used to refer to their predicates unqualified
+        assert_eq!(resolve("inside", other).as_deref(), Some("inside"));
+    }
+
+    #[test]
+    fn resolve_qualified_names() {
+        let mut i = Interpreter::new();
+        let geo = execute(&mut i, "geo.le", "module geo\ntrue => inside(x)");
+        let main = execute(&mut i, "main.le", "");
+
+        assert_eq!(
+            i.resolve("geo.inside", Span(0, 10), main).ok().as_deref(),
+            Some("geo.inside")
+        );
+        // Only the module itself can add predicates to it
+        assert_eq!(
+            i.resolve("geo.new", Span(0, 7), geo).ok().as_deref(),
+            Some("geo.new")
+        );
+        assert!(matches!(
+            i.resolve("geo.new", Span(0, 7), main),
+            Err(Error::UnknownPredicate { .. })
+        ));
+        // The span of an unknown module covers its name, in characters
+        assert!(matches!(
+            i.resolve("größe.a", Span(2, 9), main),
+            Err(Error::UnknownModule {
+                span: Span(2, 7),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn resolve_ambiguous_names() {
+        let mut i = Interpreter::new();
+        execute(&mut i, "geo.le", "module geo\ntrue => inside(x)");
+        execute(&mut i, "map.le", "module map\ntrue => inside(x)");
+        let main = execute(&mut i, "main.le", "use geo\nuse map");
+        let map = execute(&mut i, "map2.le", "module map\nuse geo");
+
+        match i.resolve("inside", Span(0, 6), main) {
+            Err(Error::AmbiguousName { candidates, .. }) => {
+                assert_eq!(candidates, ["geo.inside", "map.inside"])
+            }
+            _ => panic!("expected an ambiguous name"),
+        }
+        // The module's own predicate comes first
+        match i.resolve("inside", Span(0, 6), map) {
+            Err(Error::AmbiguousName { candidates, .. }) => {
+                assert_eq!(candidates, ["map.inside", "geo.inside"])
+            }
+            _ => panic!("expected an ambiguous name"),
+        }
+    }
+}
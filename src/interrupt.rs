@@ -0,0 +1,43 @@
+//! Cancels running questions when the user presses Ctrl-C.
+//! If no question is being resolved, Ctrl-C exits the program as usual.
+
+use crate::logic::CancellationToken;
+
+#[cfg(unix)]
+mod unix {
+    use crate::logic::CancellationToken;
+    use std::sync::OnceLock;
+
+    const SIGINT: i32 = 2;
+
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn _exit(status: i32) -> !;
+    }
+
+    extern "C" fn on_interrupt(_signum: i32) {
+        match TOKEN.get() {
+            Some(token) if token.is_active() => token.cancel(),
+            // 130 is what shells report for processes killed by SIGINT
+            _ => unsafe { _exit(130) },
+        }
+    }
+
+    pub fn init(token: CancellationToken) {
+        if TOKEN.set(token).is_ok() {
+            unsafe {
+                signal(SIGINT, on_interrupt);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn init(token: CancellationToken) {
+    unix::init(token)
+}
+
+#[cfg(not(unix))]
+pub fn init(_token: CancellationToken) {}
@@ -1,10 +1,10 @@
 use std::fmt;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
 /// Idents are hashed variable names
 pub struct Ident(pub u64);
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 /// The smallest (atomic) operand in a logical formula.
 pub enum Atom<T> {
     /// A boolean value, either `true` or `false`
@@ -13,7 +13,7 @@ pub enum Atom<T> {
     Unknown(Ident),
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 /// Objects that can be passed as arguments to predicates
 pub enum Var {
     Fixed(Ident),
@@ -23,17 +23,31 @@ pub enum Var {
 impl Atom<Var> {
     /// Pin free arguments in predicates to a fixed one
     pub fn pin_var(&mut self, to_pin: Ident, pin_to: Ident) {
-        match self {
-            Self::Predicate(_, args) => {
-                for arg in args {
-                    if let Var::Free(ident) = arg {
-                        if ident == &to_pin {
-                            *arg = Var::Fixed(pin_to);
-                        }
+        // only pin predicate args
+        if let Self::Predicate(_, args) = self {
+            for arg in args {
+                if let Var::Free(ident) = arg {
+                    if ident == &to_pin {
+                        *arg = Var::Fixed(pin_to);
                     }
                 }
             }
-            _ => {} // only pin predicate args
+        }
+    }
+
+    /// Convert the atom into a ground atom.
+    /// Returns `None` if any of the predicate arguments are still free.
+    pub fn ground(&self) -> Option<Atom<Ident>> {
+        match self {
+            Atom::Boolean(b) => Some(Atom::Boolean(*b)),
+            Atom::Unknown(ident) => Some(Atom::Unknown(*ident)),
+            Atom::Predicate(ident, args) => {
+                let fixed_args: Option<Vec<Ident>> = args
+                    .iter()
+                    .map(|arg| Ident::try_from(arg.clone()).ok())
+                    .collect();
+                Some(Atom::Predicate(*ident, fixed_args?))
+            }
         }
     }
 
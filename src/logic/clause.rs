@@ -13,13 +13,10 @@ impl<T: PartialEq> Clause<T> {
     pub fn unknowns(&self) -> Vec<Ident> {
         let mut unknowns = vec![];
         for atom in self.0.iter().flatten() {
-            match atom {
-                Atom::Unknown(ident) => {
-                    if !unknowns.contains(ident) {
-                        unknowns.push(*ident);
-                    }
+            if let Atom::Unknown(ident) = atom {
+                if !unknowns.contains(ident) {
+                    unknowns.push(*ident);
                 }
-                _ => {}
             }
         }
         unknowns
@@ -45,6 +42,76 @@ impl Clause<Var> {
         }
     }
 
+    /// Get all the free variables in the clause
+    pub fn free_vars(&self) -> Vec<Ident> {
+        let mut free_vars = vec![];
+        for atom in self.0.iter().flatten() {
+            if let Atom::Predicate(_, args) = atom {
+                for arg in args {
+                    if let Var::Free(ident) = arg {
+                        if !free_vars.contains(ident) {
+                            free_vars.push(*ident);
+                        }
+                    }
+                }
+            }
+        }
+        free_vars
+    }
+
+    /// Get all the fixed predicate arguments in the clause
+    pub fn constants(&self) -> Vec<Ident> {
+        let mut constants = vec![];
+        for atom in self.0.iter().flatten() {
+            if let Atom::Predicate(_, args) = atom {
+                for arg in args {
+                    if let Var::Fixed(ident) = arg {
+                        if !constants.contains(ident) {
+                            constants.push(*ident);
+                        }
+                    }
+                }
+            }
+        }
+        constants
+    }
+
+    /// Return every clause that can be created by pinning all free variables
+    /// to one of the given constants.
+    /// There are exponentially many of them, so they are created one at a time.
+    ///
+    /// # Example
+    /// `foo(A) => bar(A, B)` instantiated with `[x, y]` produces
+    /// `foo(x) => bar(x, x)`, `foo(x) => bar(x, y)`, `foo(y) => bar(y, x)`
+    /// and `foo(y) => bar(y, y)`
+    pub fn instantiate<'a>(&'a self, universe: &'a [Ident]) -> impl Iterator<Item = Self> + 'a {
+        let free_vars = self.free_vars();
+        // The index of the constant each free variable is pinned to
+        let mut choice = vec![0; free_vars.len()];
+        let mut exhausted = universe.is_empty() && !free_vars.is_empty();
+        std::iter::from_fn(move || {
+            if exhausted {
+                return None;
+            }
+            let mut instance = self.clone();
+            for (free_var, ix) in free_vars.iter().zip(&choice) {
+                instance.pin(*free_var, universe[*ix]);
+            }
+
+            // Advance to the next choice like an odometer, the last variable changes fastest
+            exhausted = true;
+            for ix in choice.iter_mut().rev() {
+                *ix += 1;
+                if *ix < universe.len() {
+                    exhausted = false;
+                    break;
+                }
+                *ix = 0;
+            }
+            Some(instance)
+        })
+    }
+
     /// Return a list of clauses representing the different possibilities how
     /// the given predicate can be formatted into the clause
     ///
@@ -54,17 +121,14 @@ impl Clause<Var> {
     pub fn matches(&self, predicate: (&Ident, &Vec<Var>)) -> Vec<Self> {
         let mut pin_variants = vec![];
         for atom in self.0.iter().flatten() {
-            match atom.match_predicate(predicate) {
-                Some(argmap) => {
-                    let mut cloned = self.clone();
-                    for (to_pin, pin_to) in argmap {
-                        cloned.pin(to_pin, pin_to);
-                    }
-                    if !pin_variants.contains(&cloned) {
-                        pin_variants.push(cloned);
-                    }
+            if let Some(argmap) = atom.match_predicate(predicate) {
+                let mut cloned = self.clone();
+                for (to_pin, pin_to) in argmap {
+                    cloned.pin(to_pin, pin_to);
+                }
+                if !pin_variants.contains(&cloned) {
+                    pin_variants.push(cloned);
                 }
-                None => {}
             }
         }
         pin_variants
@@ -1,8 +1,10 @@
 /// Contains utilities for managing the implication graph
 /// of the knowledge base.
 /// Adapted from <http://smallcultfollowing.com/babysteps/blog/2015/04/06/modeling-graphs-in-rust-using-vector-indices/>
+use std::collections::HashMap;
 use std::fmt;
 
+use super::limits::{Budget, Limit};
 use super::{Atom, Clause, Resolution, UnknownValue, Var};
 
 /// Index into [ImplicationGraph::atoms]
@@ -15,22 +17,29 @@ pub type NodeIndex = usize;
 pub type EdgeIndex = usize;
 
 /// Implication Graph
+#[derive(Clone)]
 pub struct ImplicationGraph {
     /// Non-divisible units in the graph
     pub atoms: Vec<Atom<Var>>,
+    /// Maps atoms to their position in [ImplicationGraph::atoms].
+    /// Since resolution overwrites atoms with their values, this always
+    /// refers to the atoms as they were inserted.
+    atom_indices: HashMap<Atom<Var>, AtomIndex>,
     /// Connected Atoms
     pub nodes: Vec<NodeData>,
+    /// Maps the atoms of a node to its position in [ImplicationGraph::nodes]
+    node_indices: HashMap<Vec<AtomIndex>, NodeIndex>,
     /// Connections (implications) between nodes
     pub edges: Vec<EdgeData>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NodeData {
     atoms: Vec<AtomIndex>,
     first_outgoing_edge: Option<EdgeIndex>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EdgeData {
     implies: NodeIndex,
     next_outgoing_edge: Option<EdgeIndex>,
@@ -40,33 +49,35 @@ impl ImplicationGraph {
     pub fn new() -> Self {
         Self {
             atoms: vec![],
+            atom_indices: HashMap::new(),
             nodes: vec![],
+            node_indices: HashMap::new(),
             edges: vec![],
         }
     }
 
     pub fn find_or_insert_atom(&mut self, atom: Atom<Var>) -> usize {
-        match self.atoms.iter().position(|a| a == &atom) {
-            Some(index) => index,
+        match self.atom_indices.get(&atom) {
+            Some(index) => *index,
             None => {
                 let index = self.atoms.len();
-                self.atoms.push(atom);
+                self.atoms.push(atom.clone());
+                self.atom_indices.insert(atom, index);
                 index
             }
         }
     }
 
     pub fn find_or_insert_node(&mut self, atoms: Vec<AtomIndex>) -> usize {
-        let to_insert = NodeData {
-            atoms: atoms,
-            first_outgoing_edge: None,
-        };
-
-        match self.nodes.iter().position(|node| node == &to_insert) {
-            Some(index) => index,
+        match self.node_indices.get(&atoms) {
+            Some(index) => *index,
             None => {
                 let index = self.nodes.len();
-                self.nodes.push(to_insert);
+                self.nodes.push(NodeData {
+                    atoms: atoms.clone(),
+                    first_outgoing_edge: None,
+                });
+                self.node_indices.insert(atoms, index);
                 index
             }
         }
@@ -98,6 +109,18 @@ impl ImplicationGraph {
         }
     }
 
+    /// Fix the value of an atom
+    pub fn assign(&mut self, atom_index: AtomIndex, value: bool) {
+        self.atoms[atom_index] = Atom::Boolean(value);
+    }
+
+    /// Find the first atom whose value is not known yet
+    pub fn first_undecided(&self) -> Option<AtomIndex> {
+        self.atoms
+            .iter()
+            .position(|atom| !matches!(atom, Atom::Boolean(_)))
+    }
+
     /// Check if the node evaluates to false trivially.
     /// That is the case if one of the atoms is `false`
     fn is_trivial_false(&self, node_index: NodeIndex) -> bool {
@@ -105,10 +128,7 @@ impl ImplicationGraph {
             .atoms
             .iter()
             .map(|atom_index| &self.atoms[*atom_index])
-            .any(|atom| match atom {
-                Atom::Boolean(false) => true,
-                _ => false,
-            })
+            .any(|atom| matches!(atom, Atom::Boolean(false)))
     }
 
     /// Check if the node evaluates to false trivially.
@@ -118,10 +138,7 @@ impl ImplicationGraph {
             .atoms
             .iter()
             .map(|atom_index| &self.atoms[*atom_index])
-            .all(|atom| match atom {
-                Atom::Boolean(true) => true,
-                _ => false,
-            })
+            .all(|atom| matches!(atom, Atom::Boolean(true)))
     }
 
     /// Set all elements
@@ -168,6 +185,13 @@ impl ImplicationGraph {
             });
         }
 
+        self.propagation_step()
+    }
+
+    /// Propagate known truth values along the edges of the graph once.
+    /// Unlike [ImplicationGraph::resolution_step], this never returns
+    /// [Resolution::Done] since there is no specific atom to resolve for.
+    pub fn propagation_step(&mut self) -> Resolution {
         let mut progressed = false;
 
         let false_index = self.find_or_insert_atom(Atom::Boolean(false));
@@ -187,10 +211,7 @@ impl ImplicationGraph {
             if self.nodes[node_index].atoms.len() != 1 {
                 self.nodes[node_index]
                     .atoms
-                    .retain(|atom_index| match self.atoms[*atom_index] {
-                        Atom::Boolean(true) => false,
-                        _ => true,
-                    });
+                    .retain(|atom_index| !matches!(self.atoms[*atom_index], Atom::Boolean(true)));
             }
 
             // if the node is true and implies other nodes,
@@ -228,7 +249,21 @@ impl ImplicationGraph {
         }
     }
 
-    pub fn successors(&self, node_index: NodeIndex) -> Successors {
+    /// Propagate truth values until no further progress can be made.
+    /// Returns `false` if the graph contains a contradiction.
+    /// Every propagation step counts as a step against the budget.
+    pub fn saturate(&mut self, budget: &mut Budget) -> Result<bool, Limit> {
+        loop {
+            budget.step(self.atoms.len())?;
+            match self.propagation_step() {
+                Resolution::Progressed => {}
+                Resolution::Contradiction => return Ok(false),
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    pub fn successors(&self, node_index: NodeIndex) -> Successors<'_> {
         Successors {
             graph: self,
             current_edge_index: self.nodes[node_index].first_outgoing_edge,
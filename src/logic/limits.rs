@@ -0,0 +1,201 @@
+//! Bounds on the resources a single question may use
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Configurable resource limits for [LogicEngine::resolve](super::LogicEngine::resolve).
+/// `None` means that the resource is unbounded.
+#[derive(Clone, Default)]
+pub struct Limits {
+    /// How many times clauses may be matched against atoms that were
+    /// themselves introduced by a matched clause
+    pub max_depth: Option<usize>,
+    /// Maximum number of atoms in the implication graph
+    pub max_atoms: Option<usize>,
+    /// Maximum number of resolution steps
+    pub max_steps: Option<usize>,
+    /// Maximum wall clock time
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The resource that ran out while resolving a question
+pub enum Limit {
+    Depth,
+    Atoms,
+    Steps,
+    Time,
+    /// Resolution was cancelled through a [CancellationToken]
+    Cancelled,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let txt = match self {
+            Limit::Depth => "maximum grounding depth",
+            Limit::Atoms => "maximum number of atoms",
+            Limit::Steps => "maximum number of resolution steps",
+            Limit::Time => "timeout",
+            Limit::Cancelled => "cancelled",
+        };
+        write!(f, "{}", txt)
+    }
+}
+
+#[derive(Clone, Default)]
+/// A handle that can be used to stop a running resolution from another
+/// thread (or a signal handler)
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    active: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Whether or not a resolution is currently observing the token
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Mark the start of a resolution, discarding any earlier cancellations
+    pub fn activate(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.active.store(true, Ordering::SeqCst);
+    }
+
+    pub fn deactivate(&self) {
+        self.active.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Keeps track of the resources used by a single resolution
+pub struct Budget<'a> {
+    limits: &'a Limits,
+    token: &'a CancellationToken,
+    started: Instant,
+    steps: usize,
+}
+
+impl<'a> Budget<'a> {
+    pub fn new(limits: &'a Limits, token: &'a CancellationToken) -> Self {
+        Self {
+            limits,
+            token,
+            started: Instant::now(),
+            steps: 0,
+        }
+    }
+
+    /// Check whether the resolution may continue with a graph of the given size
+    pub fn check(&self, num_atoms: usize) -> Result<(), Limit> {
+        if self.token.is_cancelled() {
+            return Err(Limit::Cancelled);
+        }
+        if self
+            .limits
+            .timeout
+            .is_some_and(|timeout| self.started.elapsed() > timeout)
+        {
+            return Err(Limit::Time);
+        }
+        if self.limits.max_atoms.is_some_and(|max| num_atoms > max) {
+            return Err(Limit::Atoms);
+        }
+        Ok(())
+    }
+
+    /// Account for a single resolution step
+    pub fn step(&mut self, num_atoms: usize) -> Result<(), Limit> {
+        self.steps += 1;
+        if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+            return Err(Limit::Steps);
+        }
+        self.check(num_atoms)
+    }
+
+    /// Whether or not atoms at the given depth may be expanded any further
+    pub fn may_expand(&self, depth: usize) -> bool {
+        self.limits.max_depth.is_none_or(|max| depth < max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited() {
+        let limits = Limits::default();
+        let token = CancellationToken::default();
+        let mut budget = Budget::new(&limits, &token);
+        for _ in 0..1000 {
+            assert_eq!(budget.step(1000), Ok(()));
+        }
+        assert!(budget.may_expand(1000));
+    }
+
+    #[test]
+    fn exceeded_limits() {
+        let token = CancellationToken::default();
+        let limits = Limits {
+            max_steps: Some(2),
+            ..Limits::default()
+        };
+        let mut budget = Budget::new(&limits, &token);
+        assert_eq!(budget.step(0), Ok(()));
+        assert_eq!(budget.step(0), Ok(()));
+        assert_eq!(budget.step(0), Err(Limit::Steps));
+
+        let limits = Limits {
+            max_atoms: Some(10),
+            ..Limits::default()
+        };
+        let mut budget = Budget::new(&limits, &token);
+        assert_eq!(budget.check(10), Ok(()));
+        assert_eq!(budget.check(11), Err(Limit::Atoms));
+        assert_eq!(budget.step(11), Err(Limit::Atoms));
+
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let budget = Budget::new(&limits, &token);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(budget.check(0), Err(Limit::Time));
+
+        let limits = Limits {
+            max_depth: Some(2),
+            ..Limits::default()
+        };
+        let budget = Budget::new(&limits, &token);
+        assert!(budget.may_expand(1));
+        assert!(!budget.may_expand(2));
+    }
+
+    #[test]
+    fn cancellation() {
+        let limits = Limits::default();
+        let token = CancellationToken::default();
+        token.activate();
+        let mut budget = Budget::new(&limits, &token);
+        assert_eq!(budget.step(0), Ok(()));
+
+        token.clone().cancel();
+        assert_eq!(budget.check(0), Err(Limit::Cancelled));
+        assert_eq!(budget.step(0), Err(Limit::Cancelled));
+
+        // Activating the token again starts over
+        token.activate();
+        assert!(token.is_active());
+        assert_eq!(budget.check(0), Ok(()));
+    }
+}
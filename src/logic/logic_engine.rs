@@ -1,11 +1,16 @@
-use super::{Atom, Clause, Ident, ImplicationGraph, Var};
+use super::{
+    is_satisfiable, Atom, Budget, CancellationToken, Clause, Ident, ImplicationGraph, Limit,
+    Limits, Model, ModelSearch, Var,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnknownValue {
     True,
     False,
     Either,
     Neither,
+    /// Resolution was aborted before the value could be determined
+    LimitExceeded(Limit),
 }
 
 #[derive(Debug)]
@@ -23,30 +28,258 @@ pub enum Resolution {
     Halted,
 }
 
+#[derive(Default)]
 pub struct LogicEngine {
     known_clauses: Vec<Clause<Var>>,
+    limits: Limits,
+    cancellation: CancellationToken,
 }
 
-impl Default for LogicEngine {
-    fn default() -> Self {
-        Self {
-            known_clauses: vec![],
-        }
+impl LogicEngine {
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Get a token that can be used to cancel a running resolution
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
     }
-}
 
-impl LogicEngine {
     pub fn add(&mut self, clause: Clause<Var>) {
         self.known_clauses.push(clause);
     }
 
-    pub fn resolve(&self, clause: Clause<Ident>) -> Vec<UnknownValue> {
+    pub fn clauses(&self) -> &[Clause<Var>] {
+        &self.known_clauses
+    }
+
+    /// Forget all known clauses, keeping the limits
+    pub fn clear(&mut self) {
+        self.known_clauses.clear();
+    }
+
+    /// Get all the constants used in the knowledge base and (optionally)
+    /// in a question (the herbrand universe)
+    fn universe(&self, question: Option<&Clause<Var>>) -> Vec<Ident> {
+        let mut universe = vec![];
+        for constant in self
+            .known_clauses
+            .iter()
+            .chain(question)
+            .flat_map(|c| c.constants())
+        {
+            if !universe.contains(&constant) {
+                universe.push(constant);
+            }
+        }
+        universe
+    }
+
+    /// Add every ground instance of the known clauses to an implication graph
+    fn ground_knowledge_base(
+        &self,
+        implication_graph: &mut ImplicationGraph,
+        universe: &[Ident],
+        budget: &Budget,
+    ) -> Result<(), Limit> {
+        for known_clause in &self.known_clauses {
+            for instance in known_clause.instantiate(universe) {
+                implication_graph.add_clause(instance);
+                budget.check(implication_graph.atoms.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute every ground predicate that is provably true or provably false.
+    /// Returns `None` if the knowledge base contains a contradiction.
+    pub fn materialize(&self) -> Result<Option<Model>, Limit> {
+        self.cancellation.activate();
+        let model = self.materialize_within_limits();
+        self.cancellation.deactivate();
+        model
+    }
+
+    fn materialize_within_limits(&self) -> Result<Option<Model>, Limit> {
+        let mut budget = Budget::new(&self.limits, &self.cancellation);
         let mut implication_graph = ImplicationGraph::new();
+        self.ground_knowledge_base(&mut implication_graph, &self.universe(None), &budget)?;
+
+        // Resolution overwrites atoms with their values, so remember what they were
+        let ground_atoms = implication_graph.atoms.clone();
+        if !implication_graph.saturate(&mut budget)? {
+            return Ok(None);
+        }
+
+        let mut facts = vec![];
+        for (atom, value) in ground_atoms.iter().zip(&implication_graph.atoms) {
+            if let (Atom::Predicate(..), Atom::Boolean(b)) = (atom, value) {
+                // All atoms in the graph are ground, so this can never fail
+                if let Some(ground_atom) = atom.ground() {
+                    facts.push((ground_atom, *b));
+                }
+            }
+        }
+        Ok(Some(Model(facts)))
+    }
+
+    /// Find up to `limit` consistent worlds for the question.
+    /// Every world assigns a value to the question's unknowns and to all
+    /// ground predicates in the `projection` (or to all ground predicates
+    /// if the projection is empty).
+    pub fn enumerate_models(
+        &self,
+        question: Clause<Ident>,
+        limit: usize,
+        projection: &[Ident],
+    ) -> Result<Vec<Model>, Limit> {
+        self.cancellation.activate();
+        let models = self.enumerate_models_within_limits(question, limit, projection);
+        self.cancellation.deactivate();
+        models
+    }
+
+    fn enumerate_models_within_limits(
+        &self,
+        question: Clause<Ident>,
+        limit: usize,
+        projection: &[Ident],
+    ) -> Result<Vec<Model>, Limit> {
+        let mut budget = Budget::new(&self.limits, &self.cancellation);
+        let unknowns = question.unknowns();
+        let question = question.into_varclause();
+
+        let universe = self.universe(Some(&question));
+
+        let mut implication_graph = ImplicationGraph::new();
+        implication_graph.add_clause(question);
+        self.ground_knowledge_base(&mut implication_graph, &universe, &budget)?;
+        let ground_atoms = implication_graph.atoms.clone();
+
+        // Search each combination of values for the unknowns separately,
+        // otherwise the first few worlds might all agree on the unknowns.
+        // Only the first world of each combination is used once there are
+        // `limit` combinations with a world, so the remaining ones can be skipped.
+        let mut worlds_per_assignment = vec![];
+        for values in assignments(unknowns.len()) {
+            if worlds_per_assignment.len() >= limit {
+                break;
+            }
+            let mut branch = implication_graph.clone();
+            for (unknown, value) in unknowns.iter().zip(values) {
+                let unknown_index = branch.find_or_insert_atom(Atom::Unknown(*unknown));
+                branch.assign(unknown_index, value);
+            }
+            let worlds =
+                ModelSearch::new(&ground_atoms, projection, limit).run(branch, &mut budget)?;
+            if !worlds.is_empty() {
+                worlds_per_assignment.push(worlds.into_iter());
+            }
+        }
+
+        // Interleave the worlds so that every assignment is represented
+        let mut models = vec![];
+        while models.len() < limit {
+            let mut found_any = false;
+            for worlds in worlds_per_assignment.iter_mut() {
+                if let Some(world) = worlds.next() {
+                    if models.len() < limit {
+                        models.push(world);
+                    }
+                    found_any = true;
+                }
+            }
+            if !found_any {
+                break;
+            }
+        }
+        Ok(models)
+    }
+
+    pub fn resolve(&self, clause: Clause<Ident>) -> Vec<UnknownValue> {
+        self.cancellation.activate();
+        let resolvents = self.resolve_within_limits(clause);
+        self.cancellation.deactivate();
+        resolvents
+    }
+
+    /// Determine which combinations of values for the question's unknowns
+    /// are consistent with the knowledge base.
+    /// The values in each combination are in the same order as [Clause::unknowns].
+    pub fn resolve_jointly(&self, clause: Clause<Ident>) -> Result<Vec<Vec<bool>>, Limit> {
+        self.cancellation.activate();
+        let combinations = self.resolve_jointly_within_limits(clause);
+        self.cancellation.deactivate();
+        combinations
+    }
+
+    fn resolve_jointly_within_limits(
+        &self,
+        clause: Clause<Ident>,
+    ) -> Result<Vec<Vec<bool>>, Limit> {
+        let mut budget = Budget::new(&self.limits, &self.cancellation);
         let unknowns = clause.unknowns();
-        implication_graph.add_clause(clause.into_varclause());
+        let (implication_graph, incomplete) =
+            self.ground_question(clause.into_varclause(), &budget);
+
+        // Without the full graph, we can't tell which combinations are inconsistent
+        if let Some(limit) = incomplete {
+            return Err(limit);
+        }
+
+        let mut combinations = vec![];
+        for values in assignments(unknowns.len()) {
+            budget.check(implication_graph.atoms.len())?;
+
+            let mut branch = implication_graph.clone();
+            for (unknown, value) in unknowns.iter().zip(&values) {
+                let unknown_index = branch.find_or_insert_atom(Atom::Unknown(*unknown));
+                branch.assign(unknown_index, *value);
+            }
+
+            if is_satisfiable(branch, &mut budget)? {
+                combinations.push(values);
+            }
+        }
+        Ok(combinations)
+    }
+
+    /// Build an implication graph containing the question and every ground clause
+    /// that is connected to it.
+    /// If a resource limit prevented the graph from being built completely,
+    /// that limit is returned as well.
+    fn ground_question(
+        &self,
+        question: Clause<Var>,
+        budget: &Budget,
+    ) -> (ImplicationGraph, Option<Limit>) {
+        let mut implication_graph = ImplicationGraph::new();
+        let universe = self.universe(Some(&question));
+        implication_graph.add_clause(question);
+
+        let mut incomplete = None;
+
+        // The atoms in the graph act as a table of subgoals: Every atom is a ground
+        // predicate and gets expanded exactly once, even if it is reached through
+        // several clauses. Since there are only finitely many ground predicates over
+        // the constants in the universe, this terminates even for recursive rules.
 
+        // How many matches away from the question each atom is
+        let mut depths = vec![0; implication_graph.atoms.len()];
         let mut resolved_atoms = 0;
         while resolved_atoms != implication_graph.atoms.len() {
+            if let Err(limit) = budget.check(implication_graph.atoms.len()) {
+                incomplete = Some(limit);
+                break;
+            }
+
+            let depth = depths[resolved_atoms];
+            if !budget.may_expand(depth) {
+                incomplete = Some(Limit::Depth);
+                resolved_atoms += 1;
+                continue;
+            }
+
             let mut to_add = vec![];
             match &implication_graph.atoms[resolved_atoms] {
                 Atom::Boolean(_) | Atom::Unknown(_) => {} // don't match
@@ -57,22 +290,68 @@ impl LogicEngine {
                     }
                 }
             }
-            for clause in to_add {
-                // println!("matched {:?} with {:?}", implication_graph.atoms[resolved_atoms], clause);
-                implication_graph.add_clause(clause);
+            // Variables that were not pinned by the match (like `Y` in
+            // `edge(X, Y) and path(Y, Z) => path(X, Z)` matched with `path(a, c)`)
+            // could be any constant, so every possibility needs to be considered.
+            // A single match can have a lot of instances, so the budget is checked for each.
+            let mut expansion = Ok(());
+            for instance in to_add
+                .iter()
+                .flat_map(|clause| clause.instantiate(&universe))
+            {
+                implication_graph.add_clause(instance);
+                expansion = budget.check(implication_graph.atoms.len());
+                if expansion.is_err() {
+                    break;
+                }
             }
+            depths.resize(implication_graph.atoms.len(), depth + 1);
             resolved_atoms += 1;
+            if let Err(limit) = expansion {
+                incomplete = Some(limit);
+                break;
+            }
+        }
+        (implication_graph, incomplete)
+    }
+
+    fn resolve_within_limits(&self, clause: Clause<Ident>) -> Vec<UnknownValue> {
+        let mut budget = Budget::new(&self.limits, &self.cancellation);
+        let unknowns = clause.unknowns();
+
+        // If the graph could not be fully grounded, values derived from it are still
+        // correct but the unknowns can no longer be said to be indeterminate
+        let (implication_graph, incomplete) =
+            self.ground_question(clause.into_varclause(), &budget);
+
+        // There is no point in continuing if we ran out of time
+        if let Some(limit @ (Limit::Time | Limit::Cancelled)) = incomplete {
+            return unknowns
+                .iter()
+                .map(|_| UnknownValue::LimitExceeded(limit))
+                .collect();
         }
 
         let mut resolvents = vec![];
         for unknown in unknowns {
-            let unknown_index = implication_graph.find_or_insert_atom(Atom::Unknown(unknown));
+            // Every unknown is resolved on a fresh copy of the graph, so that
+            // the values don't depend on the order in which they are resolved
+            let mut graph = implication_graph.clone();
+            let unknown_index = graph.find_or_insert_atom(Atom::Unknown(unknown));
             let mut resolved_to = None;
-            while let None = resolved_to {
-                resolved_to = match implication_graph.resolution_step(unknown_index) {
+            while resolved_to.is_none() {
+                if let Err(limit) = budget.step(graph.atoms.len()) {
+                    resolved_to = Some(UnknownValue::LimitExceeded(limit));
+                    break;
+                }
+
+                resolved_to = match graph.resolution_step(unknown_index) {
                     Resolution::Done(val) => Some(val),
                     Resolution::Contradiction => Some(UnknownValue::Neither),
-                    Resolution::Halted => Some(UnknownValue::Either),
+                    Resolution::Halted => Some(match incomplete {
+                        Some(limit) => UnknownValue::LimitExceeded(limit),
+                        None => UnknownValue::Either,
+                    }),
                     Resolution::Progressed => None,
                 };
             }
@@ -81,3 +360,37 @@ impl LogicEngine {
         resolvents
     }
 }
+
+/// Every combination of values for `n` unknowns, starting with all of them `true`.
+/// There are `2^n` combinations, so they are created one at a time.
+fn assignments(n: usize) -> impl Iterator<Item = Vec<bool>> {
+    let mut next = Some(vec![true; n]);
+    std::iter::from_fn(move || {
+        let current = next.take()?;
+        // Count in binary, with `true` as 0 and the first unknown as the lowest digit
+        let mut following = current.clone();
+        for ix in 0..n {
+            following[ix] = !following[ix];
+            if !following[ix] {
+                next = Some(following);
+                break;
+            }
+        }
+        Some(current)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_assignments() {
+        let all: Vec<Vec<bool>> = assignments(2).collect();
+        assert_eq!(
+            all,
+            [[true, true], [false, true], [true, false], [false, false]]
+        );
+        assert_eq!(assignments(0).count(), 1);
+    }
+}
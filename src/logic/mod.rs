@@ -3,9 +3,13 @@
 mod atom;
 mod clause;
 mod graph;
+mod limits;
 mod logic_engine;
+mod model;
 
 pub use atom::{Atom, Ident, Var};
 pub use clause::Clause;
 pub use graph::*;
+pub use limits::{Budget, CancellationToken, Limit, Limits};
 pub use logic_engine::{LogicEngine, Resolution, UnknownValue};
+pub use model::{is_satisfiable, Model, ModelSearch};
@@ -0,0 +1,230 @@
+//! Enumeration of consistent truth value assignments (models)
+//! for a grounded implication graph.
+
+use super::{Atom, AtomIndex, Budget, Ident, ImplicationGraph, Limit, Var};
+
+/// An assignment of truth values to ground predicates
+pub struct Model(pub Vec<(Atom<Ident>, bool)>);
+
+/// Explores the possible truth values of the atoms in an implication graph
+pub struct ModelSearch<'a> {
+    /// The atoms of the graph before any values were assigned
+    ground_atoms: &'a [Atom<Var>],
+    /// Predicates that should be part of the models.
+    /// An empty projection includes all predicates.
+    projection: &'a [Ident],
+    limit: usize,
+    models: Vec<Model>,
+}
+
+impl<'a> ModelSearch<'a> {
+    pub fn new(ground_atoms: &'a [Atom<Var>], projection: &'a [Ident], limit: usize) -> Self {
+        Self {
+            ground_atoms,
+            projection,
+            limit,
+            models: vec![],
+        }
+    }
+
+    /// Whether or not the value of an atom should be reported
+    fn is_relevant(&self, atom_index: AtomIndex) -> bool {
+        match &self.ground_atoms[atom_index] {
+            Atom::Predicate(ident, _) => {
+                self.projection.is_empty() || self.projection.contains(ident)
+            }
+            Atom::Unknown(_) => true,
+            Atom::Boolean(_) => false,
+        }
+    }
+
+    /// Find the first relevant atom whose value is not known yet
+    fn first_undecided_relevant(&self, graph: &ImplicationGraph) -> Option<AtomIndex> {
+        (0..self.ground_atoms.len())
+            .find(|ix| self.is_relevant(*ix) && !matches!(graph.atoms[*ix], Atom::Boolean(_)))
+    }
+
+    /// Search for models until either the search space is exhausted
+    /// or the limit is reached.
+    /// Every explored branch and propagation step counts as a step against the budget.
+    pub fn run(
+        mut self,
+        graph: ImplicationGraph,
+        budget: &mut Budget,
+    ) -> Result<Vec<Model>, Limit> {
+        self.search(graph, budget)?;
+        Ok(self.models)
+    }
+
+    fn search(&mut self, mut graph: ImplicationGraph, budget: &mut Budget) -> Result<(), Limit> {
+        budget.step(graph.atoms.len())?;
+        if self.models.len() >= self.limit || !graph.saturate(budget)? {
+            return Ok(());
+        }
+
+        match self.first_undecided_relevant(&graph) {
+            Some(atom_index) => {
+                for value in [true, false] {
+                    let mut branch = graph.clone();
+                    branch.assign(atom_index, value);
+                    self.search(branch, budget)?;
+                }
+            }
+            None => {
+                // All relevant atoms are decided, but the remaining ones
+                // still need to be consistent
+                if !is_satisfiable(graph.clone(), budget)? {
+                    return Ok(());
+                }
+
+                let mut assignment = vec![];
+                for (atom_index, atom) in self.ground_atoms.iter().enumerate() {
+                    if !self.is_relevant(atom_index) {
+                        continue;
+                    }
+                    if let (Some(ground_atom), Atom::Boolean(b)) =
+                        (atom.ground(), &graph.atoms[atom_index])
+                    {
+                        assignment.push((ground_atom, *b));
+                    }
+                }
+                self.models.push(Model(assignment));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if there is at least one way to assign values to all atoms
+/// in the graph without causing a contradiction.
+/// Every explored branch and propagation step counts as a step against the budget.
+pub fn is_satisfiable(mut graph: ImplicationGraph, budget: &mut Budget) -> Result<bool, Limit> {
+    budget.step(graph.atoms.len())?;
+    if !graph.saturate(budget)? {
+        return Ok(false);
+    }
+
+    match graph.first_undecided() {
+        Some(atom_index) => {
+            for value in [true, false] {
+                let mut branch = graph.clone();
+                branch.assign(atom_index, value);
+                if is_satisfiable(branch, budget)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::{CancellationToken, Clause, Limits};
+
+    /// The ground atom `p<n>(x)`
+    fn atom(n: u64) -> Atom<Var> {
+        Atom::Predicate(Ident(n), vec![Var::Fixed(Ident(0))])
+    }
+
+    /// An implication graph containing the given implication chains
+    fn graph(chains: &[&[Atom<Var>]]) -> ImplicationGraph {
+        let mut graph = ImplicationGraph::new();
+        for chain in chains {
+            let and_chains = chain.iter().map(|atom| vec![atom.clone()]).collect();
+            graph.add_clause(Clause::new(and_chains));
+        }
+        graph
+    }
+
+    /// The predicates and their values in every model, ordered like the atoms of the graph
+    fn models(
+        graph: ImplicationGraph,
+        projection: &[Ident],
+        limit: usize,
+    ) -> Vec<Vec<(u64, bool)>> {
+        let limits = Limits::default();
+        let token = CancellationToken::default();
+        let ground_atoms = graph.atoms.clone();
+        ModelSearch::new(&ground_atoms, projection, limit)
+            .run(graph, &mut Budget::new(&limits, &token))
+            .unwrap()
+            .into_iter()
+            .map(|Model(assignment)| {
+                assignment
+                    .into_iter()
+                    .filter_map(|(atom, value)| match atom {
+                        Atom::Predicate(Ident(n), _) => Some((n, value)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn satisfiable(graph: ImplicationGraph) -> bool {
+        let limits = Limits::default();
+        let token = CancellationToken::default();
+        is_satisfiable(graph, &mut Budget::new(&limits, &token)).unwrap()
+    }
+
+    const TRUE: Atom<Var> = Atom::Boolean(true);
+    const FALSE: Atom<Var> = Atom::Boolean(false);
+
+    #[test]
+    fn contradictions() {
+        let contradictory = || graph(&[&[TRUE, atom(1)], &[atom(1), atom(2)], &[atom(2), FALSE]]);
+        assert!(!satisfiable(contradictory()));
+        assert!(models(contradictory(), &[], 10).is_empty());
+    }
+
+    #[test]
+    fn determined() {
+        let determined = || graph(&[&[TRUE, atom(1)], &[atom(1), atom(2)]]);
+        assert!(satisfiable(determined()));
+        assert_eq!(models(determined(), &[], 10), [[(1, true), (2, true)]]);
+    }
+
+    #[test]
+    fn under_determined() {
+        let under_determined = || graph(&[&[atom(1), atom(2)]]);
+        assert!(satisfiable(under_determined()));
+        assert_eq!(
+            models(under_determined(), &[], 10),
+            [
+                [(1, true), (2, true)],
+                [(1, false), (2, true)],
+                [(1, false), (2, false)]
+            ]
+        );
+        // The search stops at the limit
+        assert_eq!(models(under_determined(), &[], 2).len(), 2);
+
+        // Only projected predicates are part of the models, but the others still need values
+        let models = models(under_determined(), &[Ident(1)], 10);
+        assert_eq!(models, [[(1, true)], [(1, false)]]);
+        let forced = graph(&[&[atom(1), atom(2)], &[atom(2), FALSE]]);
+        assert_eq!(
+            super::tests::models(forced, &[Ident(1)], 10),
+            [[(1, false)]]
+        );
+    }
+
+    #[test]
+    fn exceeded_limits() {
+        let limits = Limits {
+            max_steps: Some(2),
+            ..Limits::default()
+        };
+        let token = CancellationToken::default();
+        let graph = graph(&[&[atom(1), atom(2)], &[atom(3), atom(4)]]);
+        let ground_atoms = graph.atoms.clone();
+        let result = ModelSearch::new(&ground_atoms, &[], 10)
+            .run(graph.clone(), &mut Budget::new(&limits, &token));
+        assert_eq!(result.err(), Some(Limit::Steps));
+        let result = is_satisfiable(graph, &mut Budget::new(&limits, &token));
+        assert_eq!(result, Err(Limit::Steps));
+    }
+}
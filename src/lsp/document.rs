@@ -0,0 +1,487 @@
+//! Everything the language server knows about a single open document
+
+use crate::debug::lint::LintLevels;
+use crate::debug::warning::Warning;
+use crate::diagnostics::{
+    Annotation, AnnotationType, Diagnostic, Location, MessageFormat, SourceMap,
+};
+use crate::interpreter::Interpreter;
+use crate::json::Json;
+use crate::parser::parser::Parser;
+use crate::parser::span::Span;
+use crate::parser::symbol::{Atom, Line};
+use crate::repl::{self, Context, LineHandler};
+use std::fs;
+use std::io;
+use termcolor::{NoColor, WriteColor};
+
+/// A predicate that appears somewhere in a document, like `canfly(X)`
+pub struct PredicateRef {
+    pub name: String,
+    /// The arguments of the predicate, `None` for free variables
+    pub args: Vec<Option<String>>,
+    pub line: usize,
+    pub span: Span,
+    /// Whether the predicate is implied by its clause (as opposed to being a premise)
+    pub is_conclusion: bool,
+    pub in_question: bool,
+}
+
+impl PredicateRef {
+    fn signature(&self) -> String {
+        format!("{}/{}", self.name, self.args.len())
+    }
+
+    /// Whether another predicate refers to the same relation
+    fn same_relation(&self, other: &PredicateRef) -> bool {
+        self.name == other.name && self.args.len() == other.args.len()
+    }
+
+    /// Whether both predicates can be instantiated to the same ground predicate
+    fn unifies_with(&self, other: &PredicateRef) -> bool {
+        self.same_relation(other)
+            && self
+                .args
+                .iter()
+                .zip(&other.args)
+                .all(|(a, b)| a.is_none() || b.is_none() || a == b)
+    }
+}
+
+pub struct Document {
+    text: String,
+    /// Diagnostics, already in the format expected by the client
+    pub diagnostics: Vec<Json>,
+    predicates: Vec<PredicateRef>,
+    /// For every line, the forall statement whose scope the line is in (if any)
+    scopes: Vec<Option<usize>>,
+}
+
+/// Convert a char offset within a line to an LSP position (which counts UTF-16 units)
+fn position(line: &str, lineno: usize, offset: usize) -> Json {
+    let character: usize = line.chars().take(offset).map(char::len_utf16).sum();
+    Json::object([("line", lineno.into()), ("character", character.into())])
+}
+
+pub fn range(line: &str, lineno: usize, span: Span) -> Json {
+    Json::object([
+        ("start", position(line, lineno, span.0)),
+        ("end", position(line, lineno, span.1)),
+    ])
+}
+
+/// The LSP range of an annotation (or of the entire line, if there is no annotation)
+fn annotation_range(
+    diagnostic: &Diagnostic,
+    annotation: Option<&Annotation>,
+    location: Location,
+    sources: &SourceMap,
+) -> Json {
+    let at = annotation.map_or(location, |annotation| {
+        Diagnostic::annotation_location(annotation, location)
+    });
+    // Documents are analyzed without expanding tabs, so spans refer to the lines as written
+    let line = match sources.original_line(at) {
+        Some(original) if at != location => original,
+        _ => diagnostic.line(at, location, sources),
+    };
+    let span = annotation.map_or(Span(0, line.chars().count()), |annotation| annotation.span);
+    // Line numbers in diagnostics start at 1, in LSP they start at 0
+    range(line, at.line - 1, span)
+}
+
+/// Convert a diagnostic into an LSP diagnostic.
+/// The first annotation determines the range, all others become related information.
+fn lsp_diagnostic(
+    diagnostic: &Diagnostic,
+    location: Location,
+    sources: &SourceMap,
+    uri: &str,
+) -> Json {
+    let mut annotations = diagnostic.annotations.iter();
+    let primary = annotations.next();
+
+    let mut message = diagnostic.msg.clone();
+    if let Some(primary) = primary {
+        message.push_str(&format!("\n{}", primary.msg));
+    }
+    if let Some(note) = diagnostic.note {
+        message.push_str(&format!("\nnote: {}", note));
+    }
+
+    let severity: usize = match diagnostic.annotation_type {
+        AnnotationType::Error => 1,
+        AnnotationType::Warning => 2,
+        AnnotationType::Info | AnnotationType::Note => 3,
+        AnnotationType::Help => 4,
+    };
+
+    // Annotations in imported files can't be shown in this document
+    let related: Vec<Json> = annotations
+        .filter(|annotation| {
+            Diagnostic::annotation_location(annotation, location).source == location.source
+        })
+        .map(|annotation| {
+            Json::object([
+                (
+                    "location",
+                    Json::object([
+                        ("uri", uri.into()),
+                        (
+                            "range",
+                            annotation_range(diagnostic, Some(annotation), location, sources),
+                        ),
+                    ]),
+                ),
+                ("message", annotation.msg.as_str().into()),
+            ])
+        })
+        .collect();
+
+    Json::object([
+        (
+            "range",
+            annotation_range(diagnostic, primary, location, sources),
+        ),
+        ("severity", severity.into()),
+        ("code", diagnostic.code.into()),
+        ("source", "leuchtkraft".into()),
+        ("message", message.into()),
+        ("relatedInformation", Json::Array(related)),
+    ])
+}
+
+/// Convert all warnings that aren't allowed to LSP diagnostics
+fn push_warnings(
+    interpreter: &Interpreter,
+    warnings: &[Warning],
+    line: &str,
+    location: Location,
+    uri: &str,
+    diagnostics: &mut Vec<Json>,
+) {
+    for warning in warnings {
+        if let Some(diagnostic) = interpreter.lint(warning, line) {
+            // Warnings about imported files are shown when those files are opened
+            if diagnostic.primary_location(location).source != location.source {
+                continue;
+            }
+            diagnostics.push(lsp_diagnostic(
+                &diagnostic,
+                location,
+                interpreter.sources(),
+                uri,
+            ));
+        }
+    }
+}
+
+/// Imported files are only checked, so there are no answers to handle
+struct IgnoreAnswers;
+
+impl LineHandler for IgnoreAnswers {
+    fn executed<W: WriteColor>(
+        &mut self,
+        _: &mut Interpreter,
+        _: &str,
+        _: Location,
+        _: Option<String>,
+        _: &mut W,
+        _: MessageFormat,
+    ) {
+    }
+}
+
+impl Document {
+    /// Check a document for errors and collect all the predicates in it
+    pub fn analyze(uri: &str, text: String, lint_levels: LintLevels) -> Self {
+        let mut diagnostics = vec![];
+        let mut predicates = vec![];
+        let mut scopes = vec![];
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_check_only(true);
+        interpreter.set_lint_levels(lint_levels);
+        let mut free_vars: Vec<&str> = vec![];
+        let mut scope = None;
+        // Imports are resolved relative to the document, just like when it is executed
+        let context = match super::file_path(uri) {
+            Some(path) => {
+                // Importing the document itself is a cycle
+                interpreter.enter_file(fs::canonicalize(&path).unwrap_or_else(|_| path.clone()));
+                Context::File(path)
+            }
+            None => Context::Repl,
+        };
+        let source_id = interpreter.add_source(context);
+        let mut last_location = None;
+
+        for (lineno, line) in text.lines().enumerate() {
+            let mut warnings = vec![];
+            let location = interpreter.push_line(source_id, line);
+            last_location = Some(location);
+            if let Err(error) = interpreter.execute(line, location, &mut warnings) {
+                let sources = interpreter.sources();
+                diagnostics.push(lsp_diagnostic(&error, location, sources, uri));
+            }
+            push_warnings(
+                &interpreter,
+                &warnings,
+                line,
+                location,
+                uri,
+                &mut diagnostics,
+            );
+
+            if let Some(import) = interpreter.take_import() {
+                // Diagnostics in the imported file are shown when it is opened
+                let mut discard = NoColor::new(io::sink());
+                let result = repl::import_file(
+                    &mut interpreter,
+                    import,
+                    location,
+                    &mut IgnoreAnswers,
+                    &mut discard,
+                    MessageFormat::Human,
+                );
+                if let Err(error) = result {
+                    let diagnostic = Diagnostic::from((error, line));
+                    let sources = interpreter.sources();
+                    diagnostics.push(lsp_diagnostic(&diagnostic, location, sources, uri));
+                }
+            }
+
+            // The interpreter already reported any warnings, no need to collect them twice
+            let statement = Parser::new(line).line(&mut vec![]);
+            if let Ok(Some(parsed_line)) = statement.map(|statement| statement.line) {
+                match parsed_line.into_inner() {
+                    Line::Forall(idents) => {
+                        free_vars = idents.iter().map(|ident| *ident.as_inner()).collect();
+                        scope = Some(lineno);
+                    }
+                    Line::Import(_) | Line::Module(_) | Line::Use(_) => {
+                        free_vars.clear();
+                        scope = None;
+                    }
+                    Line::Rule(is_indented, is_question, and_chains) => {
+                        if !is_indented {
+                            free_vars.clear();
+                            scope = None;
+                        }
+                        for (chain_ix, atom) in and_chains
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(ix, chain)| chain.iter().map(move |atom| (ix, atom)))
+                        {
+                            if let Atom::Predicate(name, args) = atom.as_inner() {
+                                predicates.push(PredicateRef {
+                                    name: (*name).to_owned(),
+                                    args: args
+                                        .iter()
+                                        .map(|arg| *arg.as_inner())
+                                        .map(|arg| {
+                                            (!free_vars.contains(&arg)).then(|| arg.to_owned())
+                                        })
+                                        .collect(),
+                                    line: lineno,
+                                    span: atom.span(),
+                                    is_conclusion: chain_ix != 0,
+                                    in_question: is_question,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            scopes.push(scope);
+        }
+
+        let mut warnings = vec![];
+        interpreter.finish(&mut warnings);
+        // All remaining warnings refer to earlier lines
+        if let Some(location) = last_location {
+            push_warnings(&interpreter, &warnings, "", location, uri, &mut diagnostics);
+        }
+
+        Self {
+            text,
+            diagnostics,
+            predicates,
+            scopes,
+        }
+    }
+
+    pub fn line(&self, lineno: usize) -> Option<&str> {
+        self.text.lines().nth(lineno)
+    }
+
+    /// Find the predicate at an LSP position
+    pub fn predicate_at(&self, lineno: usize, character: usize) -> Option<&PredicateRef> {
+        let line = self.line(lineno)?;
+
+        // Convert from UTF-16 units back to chars
+        let mut units = 0;
+        let offset = line
+            .chars()
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= character
+            })
+            .count();
+
+        self.predicates.iter().find(|predicate| {
+            predicate.line == lineno && predicate.span.0 <= offset && offset <= predicate.span.1
+        })
+    }
+
+    /// All places where the predicate's relation is concluded outside of a question
+    fn definitions<'a>(
+        &'a self,
+        predicate: &'a PredicateRef,
+    ) -> impl Iterator<Item = &'a PredicateRef> {
+        self.predicates.iter().filter(move |other| {
+            other.is_conclusion && !other.in_question && other.same_relation(predicate)
+        })
+    }
+
+    /// All definitions that could be used to prove the given predicate
+    pub fn matching_definitions<'a>(
+        &'a self,
+        predicate: &'a PredicateRef,
+    ) -> impl Iterator<Item = &'a PredicateRef> {
+        self.definitions(predicate)
+            .filter(move |definition| definition.unifies_with(predicate))
+    }
+
+    /// Describe a predicate along with the clauses defining it, as markdown
+    pub fn hover(&self, predicate: &PredicateRef) -> String {
+        let mut lines: Vec<usize> = self.definitions(predicate).map(|d| d.line).collect();
+        lines.dedup();
+
+        if lines.is_empty() {
+            return format!("`{}` is never defined", predicate.signature());
+        }
+
+        let mut clauses = vec![];
+        let mut current_scope = None;
+        for lineno in lines {
+            // Clauses inside a forall block are shown together with the forall statement
+            let scope = self.scopes[lineno];
+            if scope.is_some() && scope != current_scope {
+                clauses.extend(scope.and_then(|forall| self.line(forall)));
+            }
+            current_scope = scope;
+            clauses.extend(self.line(lineno));
+        }
+        format!(
+            "`{}`\n```leuchtkraft\n{}\n```",
+            predicate.signature(),
+            clauses.join("\n")
+        )
+    }
+
+    /// The names and arities of all predicates in the document, without duplicates
+    pub fn known_predicates(&self) -> Vec<(&str, usize)> {
+        let mut known = vec![];
+        for predicate in &self.predicates {
+            let signature = (predicate.name.as_str(), predicate.args.len());
+            if !known.contains(&signature) {
+                known.push(signature);
+            }
+        }
+        known
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn analyze(text: &str) -> Document {
+        Document::analyze("untitled:test", text.to_owned(), LintLevels::default())
+    }
+
+    /// A directory with the given files, for documents that import them
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, text) in files {
+            fs::write(dir.join(file), text).unwrap();
+        }
+        dir
+    }
+
+    fn codes(document: &Document) -> Vec<&str> {
+        document
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.get("code").and_then(Json::as_str))
+            .collect()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let document = analyze("true => a(x) and true\nb(x\n");
+        assert_eq!(codes(&document), ["W002", "E001"]);
+
+        let range = document.diagnostics[1].get("range").unwrap();
+        let start = range.get("start").unwrap();
+        assert_eq!(start.get("line").and_then(Json::as_usize), Some(1));
+    }
+
+    #[test]
+    fn predicates() {
+        let document = analyze("forall X\n    bird(X) => canfly(X)\ntrue => bird(tweety)\n");
+        let predicate = document.predicate_at(2, 9).unwrap();
+        assert_eq!(predicate.signature(), "bird/1");
+        assert!(document.predicate_at(2, 2).is_none());
+
+        // Only the fact concludes `bird(tweety)`, the rule just uses it
+        let definitions: Vec<usize> = document
+            .matching_definitions(predicate)
+            .map(|definition| definition.line)
+            .collect();
+        assert_eq!(definitions, [2]);
+        let canfly = document.predicate_at(1, 16).unwrap();
+        assert_eq!(canfly.args, [None]);
+        assert!(document
+            .hover(canfly)
+            .contains("forall X\n    bird(X) => canfly(X)"));
+
+        assert_eq!(document.known_predicates(), [("bird", 1), ("canfly", 1)]);
+    }
+
+    #[test]
+    fn positions_count_utf16_units() {
+        let document = analyze("true => 𝕏(x) and größe(x)\n");
+        // '𝕏' takes up two UTF-16 units
+        let predicate = document.predicate_at(0, 18).unwrap();
+        assert_eq!(predicate.name, "größe");
+        let range = super::range(document.line(0).unwrap(), 0, predicate.span);
+        let start = range.get("start").and_then(|start| start.get("character"));
+        assert_eq!(start.and_then(Json::as_usize), Some(18));
+    }
+
+    #[test]
+    fn imports() {
+        let dir = directory(
+            "leuchtkraft_document_imports",
+            &[("geo.le", "module geo\ntrue => inside(berlin)\n")],
+        );
+        let uri = format!("file://{}/main.le", dir.display());
+        let text = "import \"geo.le\"\nuse geo\nX? => inside(berlin) => X?\n";
+        let document = Document::analyze(&uri, text.to_owned(), LintLevels::default());
+        assert_eq!(codes(&document), Vec::<&str>::new());
+
+        let text = "import \"missing.le\"\n";
+        let document = Document::analyze(&uri, text.to_owned(), LintLevels::default());
+        assert_eq!(codes(&document), ["E008"]);
+
+        // A document importing itself
+        let text = "import \"main.le\"\n";
+        fs::write(dir.join("main.le"), text).unwrap();
+        let document = Document::analyze(&uri, text.to_owned(), LintLevels::default());
+        assert_eq!(codes(&document), ["E009"]);
+    }
+}
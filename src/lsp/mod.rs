@@ -0,0 +1,547 @@
+//! A language server for Leuchtkraft scripts, speaking the
+//! [Language Server Protocol](https://microsoft.github.io/language-server-protocol/) over stdio.
+//!
+//! Supported features:
+//! * Parse errors and warnings are published as diagnostics
+//! * Hovering over a predicate shows the clauses defining it
+//! * Go-to-definition jumps from a predicate to the clauses that could prove it
+//! * Completion of predicate names
+
+mod document;
+
+use crate::debug::lint::LintLevels;
+use crate::json::Json;
+use document::{range, Document};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+// Error codes defined by JSON-RPC
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// An error that is sent back to the client
+struct ResponseError {
+    code: i32,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params() -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: "Invalid params".to_owned(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    /// All open documents by uri
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+}
+
+/// Read a single message.
+/// Returns `None` once the client closed the connection.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn send<W: Write>(writer: &mut W, message: Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+fn respond<W: Write>(
+    writer: &mut W,
+    id: Json,
+    result: Result<Json, ResponseError>,
+) -> io::Result<()> {
+    let payload = match result {
+        Ok(result) => ("result", result),
+        Err(error) => (
+            "error",
+            Json::object([
+                ("code", Json::Number(error.code.into())),
+                ("message", error.message.into()),
+            ]),
+        ),
+    };
+    send(
+        writer,
+        Json::object([("jsonrpc", "2.0".into()), ("id", id), payload]),
+    )
+}
+
+fn notify<W: Write>(writer: &mut W, method: &str, params: Json) -> io::Result<()> {
+    send(
+        writer,
+        Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]),
+    )
+}
+
+/// Extract the document uri and the position from the params of a request
+fn text_document_position(params: &Json) -> Option<(&str, usize, usize)> {
+    let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+    let position = params.get("position")?;
+    let line = position.get("line")?.as_usize()?;
+    let character = position.get("character")?.as_usize()?;
+    Some((uri, line, character))
+}
+
+/// Convert a `file://` uri into a path, decoding percent-encoded characters.
+/// Returns `None` for files on other hosts and malformed uris.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // The host is either empty (`file:///home`) or `localhost`
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Load the lint levels for a document from the closest config file.
+/// Documents that aren't files (like unsaved buffers) use the default levels,
+/// file uris that can't be mapped to a path are an error.
+/// Invalid config files are ignored.
+fn lint_levels(uri: &str) -> Result<LintLevels, String> {
+    if !uri.starts_with("file:") {
+        return Ok(LintLevels::default());
+    }
+    let path = file_path(uri).ok_or_else(|| {
+        format!(
+            "Could not map {} to a path, the default lint levels are used",
+            uri
+        )
+    })?;
+    Ok(path
+        .parent()
+        .and_then(|dir| LintLevels::discover(dir).ok())
+        .unwrap_or_default())
+}
+
+impl Server {
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, ResponseError> {
+        match method {
+            "initialize" => Ok(Json::object([
+                (
+                    "capabilities",
+                    Json::object([
+                        ("textDocumentSync", 1_usize.into()), // the client always sends the full text
+                        ("hoverProvider", true.into()),
+                        ("definitionProvider", true.into()),
+                        ("completionProvider", Json::object([])),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object([
+                        ("name", "leuchtkraft".into()),
+                        ("version", env!("CARGO_PKG_VERSION").into()),
+                    ]),
+                ),
+            ])),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => {
+                let (uri, line, character) =
+                    text_document_position(params).ok_or_else(ResponseError::invalid_params)?;
+                let hover = self.documents.get(uri).and_then(|document| {
+                    let predicate = document.predicate_at(line, character)?;
+                    Some(Json::object([
+                        (
+                            "contents",
+                            Json::object([
+                                ("kind", "markdown".into()),
+                                ("value", document.hover(predicate).into()),
+                            ]),
+                        ),
+                        ("range", range(document.line(line)?, line, predicate.span)),
+                    ]))
+                });
+                Ok(hover.unwrap_or(Json::Null))
+            }
+            "textDocument/definition" => {
+                let (uri, line, character) =
+                    text_document_position(params).ok_or_else(ResponseError::invalid_params)?;
+                let mut locations = vec![];
+                if let Some(document) = self.documents.get(uri) {
+                    if let Some(predicate) = document.predicate_at(line, character) {
+                        for definition in document.matching_definitions(predicate) {
+                            let text = document.line(definition.line).unwrap_or_default();
+                            locations.push(Json::object([
+                                ("uri", uri.into()),
+                                ("range", range(text, definition.line, definition.span)),
+                            ]));
+                        }
+                    }
+                }
+                Ok(Json::Array(locations))
+            }
+            "textDocument/completion" => {
+                let (uri, ..) =
+                    text_document_position(params).ok_or_else(ResponseError::invalid_params)?;
+                let items = self
+                    .documents
+                    .get(uri)
+                    .map(|document| document.known_predicates())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(name, arity)| {
+                        Json::object([
+                            ("label", name.into()),
+                            ("kind", 3_usize.into()), // Function
+                            ("detail", format!("{}/{}", name, arity).into()),
+                        ])
+                    })
+                    .collect();
+                Ok(Json::Array(items))
+            }
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method {:?}", method),
+            }),
+        }
+    }
+
+    fn handle_notification<W: Write>(
+        &mut self,
+        method: &str,
+        params: &Json,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str);
+
+        // Notifications can't be answered, so invalid ones are silently dropped
+        let text = match method {
+            "textDocument/didOpen" => params
+                .get("textDocument")
+                .and_then(|document| document.get("text"))
+                .and_then(Json::as_str),
+            // Since we only support full synchronization, the last change contains the entire text
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text"))
+                .and_then(Json::as_str),
+            "textDocument/didClose" => {
+                if let Some(uri) = uri {
+                    self.documents.remove(uri);
+                    self.publish_diagnostics(uri, vec![], writer)?;
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        if let (Some(uri), Some(text)) = (uri, text) {
+            let lint_levels = match lint_levels(uri) {
+                Ok(lint_levels) => lint_levels,
+                Err(message) => {
+                    notify(
+                        writer,
+                        "window/logMessage",
+                        Json::object([
+                            ("type", 2_usize.into()), // Warning
+                            ("message", message.into()),
+                        ]),
+                    )?;
+                    LintLevels::default()
+                }
+            };
+            let document = Document::analyze(uri, text.to_owned(), lint_levels);
+            self.publish_diagnostics(uri, document.diagnostics.clone(), writer)?;
+            self.documents.insert(uri.to_owned(), document);
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics<W: Write>(
+        &self,
+        uri: &str,
+        diagnostics: Vec<Json>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        notify(
+            writer,
+            "textDocument/publishDiagnostics",
+            Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+}
+
+/// Serve a single client until it sends the `exit` notification or closes the connection.
+/// Returns whether or not the client asked the server to shut down before exiting.
+pub fn run<R: BufRead, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<bool> {
+    let mut server = Server::default();
+
+    while let Some(content) = read_message(reader)? {
+        let message = match Json::parse(&content) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = ResponseError {
+                    code: PARSE_ERROR,
+                    message: format!("Invalid JSON at position {}", e.position),
+                };
+                respond(writer, Json::Null, Err(error))?;
+                continue;
+            }
+        };
+
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let method = message.get("method").and_then(Json::as_str);
+        match (message.get("id").cloned(), method) {
+            (_, Some("exit")) => break,
+            (Some(id), Some(method)) => {
+                let result = server.handle_request(method, &params);
+                respond(writer, id, result)?;
+            }
+            (None, Some(method)) => server.handle_notification(method, &params, writer)?,
+            // Responses to requests we never sent
+            (Some(_), None) => {}
+            (None, None) => {
+                let error = ResponseError {
+                    code: INVALID_REQUEST,
+                    message: "Invalid request".to_owned(),
+                };
+                respond(writer, Json::Null, Err(error))?;
+            }
+        }
+    }
+    Ok(server.shutdown_requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::lint::CONFIG_FILE;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    /// Frame the messages like a client would
+    fn frame(messages: &[String]) -> Vec<u8> {
+        let mut framed = vec![];
+        for message in messages {
+            send(&mut framed, Json::parse(message).unwrap()).unwrap();
+        }
+        framed
+    }
+
+    /// All messages sent by the server
+    fn messages(output: Vec<u8>) -> Vec<Json> {
+        let mut reader = Cursor::new(output);
+        let mut messages = vec![];
+        while let Some(content) = read_message(&mut reader).unwrap() {
+            messages.push(Json::parse(&content).unwrap());
+        }
+        messages
+    }
+
+    #[test]
+    fn session() {
+        let dir = std::env::temp_dir().join("leuchtkraft lsp session");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(CONFIG_FILE),
+            "[lints]\nredundant_true = \"allow\"\n",
+        )
+        .unwrap();
+        let uri = format!("file://{}/birds.le", dir.display()).replace(' ', "%20");
+        let text = "forall X\\n    bird(X) => canfly(X)\\ntrue => bird(john) and true\\nX? => canfly(john) => X?\\n";
+        let position = |id: usize, method: &str, line: usize, character: usize| {
+            format!(
+                r#"{{"jsonrpc": "2.0", "id": {}, "method": "{}", "params": {{"textDocument": {{"uri": "{}"}}, "position": {{"line": {}, "character": {}}}}}}}"#,
+                id, method, uri, line, character
+            )
+        };
+
+        let input = frame(&[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#.to_owned(),
+            r#"{"jsonrpc": "2.0", "method": "initialized", "params": {}}"#.to_owned(),
+            format!(
+                r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "{}", "text": "{}"}}}}}}"#,
+                uri, text
+            ),
+            // Without a config file, the redundant `true` is reported
+            format!(
+                r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "untitled:Untitled-1", "text": "{}"}}}}}}"#,
+                text
+            ),
+            position(2, "textDocument/hover", 3, 8),
+            position(3, "textDocument/definition", 3, 8),
+            position(4, "textDocument/completion", 3, 0),
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "shutdown"}"#.to_owned(),
+            r#"{"jsonrpc": "2.0", "method": "exit"}"#.to_owned(),
+        ]);
+
+        let mut output = vec![];
+        assert!(run(&mut Cursor::new(input), &mut output).unwrap());
+        let messages = messages(output);
+        assert_eq!(messages.len(), 7);
+
+        let result = |ix: usize, id: usize| {
+            assert_eq!(messages[ix].get("id").and_then(Json::as_usize), Some(id));
+            messages[ix].get("result").unwrap()
+        };
+        let diagnostics = |ix: usize| {
+            let message = &messages[ix];
+            assert_eq!(
+                message.get("method").and_then(Json::as_str),
+                Some("textDocument/publishDiagnostics")
+            );
+            message.get("params")?.get("diagnostics")?.as_array()
+        };
+
+        let capabilities = result(0, 1).get("capabilities").unwrap();
+        assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+        assert_eq!(diagnostics(1).map(<[Json]>::len), Some(0));
+        assert_eq!(diagnostics(2).map(<[Json]>::len), Some(1));
+
+        let hover = result(3, 2).get("contents").and_then(|c| c.get("value"));
+        let hover = hover.and_then(Json::as_str).unwrap();
+        assert!(hover.contains("    bird(X) => canfly(X)"));
+
+        let definitions = result(4, 3).as_array().unwrap();
+        assert_eq!(definitions.len(), 1);
+        let start = definitions[0].get("range").and_then(|r| r.get("start"));
+        assert_eq!(
+            start.and_then(|s| s.get("line")).and_then(Json::as_usize),
+            Some(1)
+        );
+
+        let completions = result(5, 4).as_array().unwrap();
+        let labels: Vec<&str> = completions
+            .iter()
+            .filter_map(|item| item.get("label").and_then(Json::as_str))
+            .collect();
+        assert_eq!(labels, ["bird", "canfly"]);
+
+        assert_eq!(result(6, 5), &Json::Null);
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(
+            file_path("file:///home/me/my%20scripts/b%C3%A4r.le").as_deref(),
+            Some(Path::new("/home/me/my scripts/bär.le"))
+        );
+        assert_eq!(
+            file_path("file://localhost/a.le").as_deref(),
+            Some(Path::new("/a.le"))
+        );
+        assert_eq!(file_path("file://server/share/a.le"), None);
+        assert_eq!(file_path("file:///a%2.le"), None);
+        assert_eq!(file_path("untitled:Untitled-1"), None);
+
+        assert!(lint_levels("untitled:Untitled-1").is_ok());
+        assert!(lint_levels("file:///a%ff.le").is_err());
+    }
+
+    #[test]
+    fn modules_across_imports() {
+        let dir = std::env::temp_dir().join("leuchtkraft_lsp_modules");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("geo.le"), "module geo\ntrue => inside(berlin)\n").unwrap();
+        fs::write(dir.join("map.le"), "module map\ntrue => inside(paris)\n").unwrap();
+        let did_open = |name: &str, text: &str| {
+            format!(
+                r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "file://{}/{}", "text": "{}"}}}}}}"#,
+                dir.display(),
+                name,
+                text
+            )
+        };
+
+        let input = frame(&[
+            did_open(
+                "main.le",
+                r#"import \"geo.le\"\nuse geo\nX? => inside(berlin) and geo.inside(berlin) => X?"#,
+            ),
+            // Names in both used modules are ambiguous
+            did_open(
+                "both.le",
+                r#"import \"geo.le\"\nimport \"map.le\"\nuse geo\nuse map\nX? => inside(berlin) => X?"#,
+            ),
+            // Without the import, the module is unknown
+            did_open("unknown.le", "use geo"),
+        ]);
+        let mut output = vec![];
+        run(&mut Cursor::new(input), &mut output).unwrap();
+
+        let messages = messages(output);
+        let codes: Vec<Vec<&str>> = messages
+            .iter()
+            .map(|message| {
+                let diagnostics = message.get("params").and_then(|p| p.get("diagnostics"));
+                diagnostics
+                    .and_then(Json::as_array)
+                    .unwrap()
+                    .iter()
+                    .filter_map(|diagnostic| diagnostic.get("code").and_then(Json::as_str))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(codes, [vec![], vec!["E012"], vec!["E010"]]);
+    }
+}
@@ -3,52 +3,354 @@
 mod cli;
 mod debug;
 mod diagnostics;
+mod exit;
+mod fix;
+mod formatter;
 mod interpreter;
+mod interrupt;
+mod json;
 mod logic;
+mod lsp;
 mod parser;
 mod repl;
+mod symbol_table;
+mod test_runner;
 mod util;
 
+use debug::lint::LintLevels;
 use debug::panic;
-use interpreter::Interpreter;
+use diagnostics::{DisplayDiagnostic, MessageFormat, SourceMap};
+use exit::ExitStatus;
+use interpreter::{AnswerMode, Interpreter};
 use repl::{run_repl, Context, Repl};
 use std::fs;
+use std::path::{Path, PathBuf};
 use termcolor::{ColorChoice, StandardStream};
 
 fn main() {
     panic::init(); // Initialize custom panic handler
 
-    if let Some(options) = cli::Options::from_args() {
-        let colors = if options.no_color {
-            ColorChoice::Never
-        } else {
-            ColorChoice::Auto
-        };
-        let mut stdout = StandardStream::stdout(colors);
+    let options = match cli::Options::from_args() {
+        Ok(options) => options,
+        Err(status) => status.exit(),
+    };
+    let colors = if options.no_color {
+        ColorChoice::Never
+    } else {
+        ColorChoice::Auto
+    };
+    // Answers go to stdout, diagnostics to stderr
+    let mut stdout = StandardStream::stdout(colors);
+    let mut stderr = StandardStream::stderr(colors);
 
-        let mut i = Interpreter::new();
-        if let Some(filename) = options.file_name {
-            let file = match fs::read_to_string(&filename) {
-                Ok(file) => file,
-                Err(e) => {
-                    println!("Could not read {}:\n{:?}", filename.display(), e);
-                    return;
+    if let cli::Command::Test(paths) = &options.command {
+        match test_runner::run_tests(
+            paths,
+            &options.lint_levels,
+            &mut stdout,
+            &mut stderr,
+            options.message_format,
+        ) {
+            Ok(true) => return,
+            Ok(false) => ExitStatus::ExpectationFailed.exit(),
+            Err(e) => {
+                eprintln!("Could not run tests:\n{:?}", e);
+                ExitStatus::IoError.exit();
+            }
+        }
+    }
+
+    if let cli::Command::Explain(code) = &options.command {
+        match debug::explain::explanation(code) {
+            Some(explanation) => print!("{}", explanation),
+            None => {
+                eprintln!("There is no diagnostic with the code {:?}", code);
+                let codes: Vec<&str> = debug::explain::codes().collect();
+                eprintln!("Known codes are: {}", codes.join(", "));
+                ExitStatus::Error.exit();
+            }
+        }
+        return;
+    }
+
+    if let cli::Command::Lsp = &options.command {
+        // stdout belongs to the client, so errors can only be reported on stderr
+        match lsp::run(&mut std::io::stdin().lock(), &mut std::io::stdout().lock()) {
+            Ok(true) => return,
+            Ok(false) => ExitStatus::Error.exit(),
+            Err(e) => {
+                eprintln!("Language server failed:\n{:?}", e);
+                ExitStatus::IoError.exit();
+            }
+        }
+    }
+
+    if let cli::Command::Fix(paths) = &options.command {
+        fix_scripts(paths, &options.lint_levels).exit();
+    }
+
+    if let cli::Command::Check(paths) = &options.command {
+        check_scripts(
+            paths,
+            &options.lint_levels,
+            &mut stderr,
+            options.message_format,
+        )
+        .exit();
+    }
+
+    if let cli::Command::Fmt { paths, check } = &options.command {
+        format_scripts(paths, *check, &mut stderr, options.message_format).exit();
+    }
+
+    // The config file closest to the script (or the working directory) is used
+    let config_dir = match options.file_names.first() {
+        Some(filename) => filename.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => PathBuf::from("."),
+    };
+    let mut lint_levels = match LintLevels::discover(&config_dir) {
+        Ok(lint_levels) => lint_levels,
+        Err((path, msg)) => {
+            eprintln!("Could not load {}:\n{}", path.display(), msg);
+            ExitStatus::Error.exit();
+        }
+    };
+    lint_levels.extend(&options.lint_levels);
+
+    let mut i = Interpreter::new();
+    i.set_limits(options.limits);
+    i.set_lint_levels(lint_levels);
+    if options.joint {
+        i.set_answer_mode(AnswerMode::Joint);
+    }
+    interrupt::init(i.cancellation_token());
+    if !options.file_names.is_empty() || !options.queries.is_empty() {
+        let mut status = ExitStatus::Success;
+        // Only the answers to the queries are printed, the scripts' own questions are just checked
+        i.set_check_only(!options.queries.is_empty());
+        for filename in &options.file_names {
+            let file_status =
+                match repl::run_file(&mut i, filename, &mut stderr, options.message_format) {
+                    Ok(file_status) => file_status,
+                    Err(e) => {
+                        eprintln!("Could not read {}:\n{:?}", filename.display(), e);
+                        ExitStatus::IoError.exit();
+                    }
+                };
+            status = status.and(file_status);
+            // An error stops the remaining files just like the rest of the file it occurred in
+            if !matches!(file_status, ExitStatus::Success | ExitStatus::Contradiction) {
+                break;
+            }
+        }
+
+        if options.materialize {
+            match i.materialize() {
+                Ok(Some(program)) => print!("{}", program),
+                Ok(None) => {
+                    eprintln!("Cannot materialize a contradictory knowledge base");
+                    status = status.and(ExitStatus::Contradiction);
+                }
+                Err(limit) => {
+                    eprintln!(
+                        "Cannot materialize the knowledge base (resource limit exceeded: {})",
+                        limit
+                    );
+                    status = status.and(ExitStatus::Error);
                 }
-            };
+            }
+        }
 
+        i.set_check_only(false);
+        // Answers would be misleading if not all of the files were executed
+        if matches!(status, ExitStatus::Success | ExitStatus::Contradiction) {
+            status = status.and(repl::run_queries(
+                &mut i,
+                &options.queries,
+                options.values_only,
+                &mut stderr,
+                options.message_format,
+            ));
+        }
+
+        if options.interactive {
+            // Mistakes made in the repl don't affect the exit status
             run_repl(
                 &mut i,
-                file.lines().map(|l| l.to_owned()),
-                Context::File(filename),
-                &mut stdout,
+                Repl::new(),
+                Context::Repl,
+                &mut stderr,
+                options.message_format,
             );
+        }
+
+        status.exit();
+    } else {
+        // Enter a REPL
+        run_repl(
+            &mut i,
+            Repl::new(),
+            Context::Repl,
+            &mut stderr,
+            options.message_format,
+        );
+    }
+}
+
+/// Format all scripts in the given paths.
+/// Fails if a script could not be formatted or, in check mode,
+/// if a script is not formatted correctly.
+fn format_scripts(
+    paths: &[PathBuf],
+    check: bool,
+    stderr: &mut StandardStream,
+    format: MessageFormat,
+) -> ExitStatus {
+    let mut scripts = vec![];
+    for path in paths {
+        if let Err(e) = util::collect_scripts(path, &mut scripts) {
+            eprintln!("Could not read {}:\n{:?}", path.display(), e);
+            return ExitStatus::IoError;
+        }
+    }
+
+    let mut status = ExitStatus::Success;
+    for script in scripts {
+        let source = match fs::read_to_string(&script) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Could not read {}:\n{:?}", script.display(), e);
+                status = status.and(ExitStatus::IoError);
+                continue;
+            }
+        };
+
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err((lineno, diagnostic)) => {
+                let mut sources = SourceMap::default();
+                let source_id = sources.add(Context::File(script.clone()));
+                let location = source
+                    .lines()
+                    .take(lineno)
+                    .map(|line| sources.push_line(source_id, line))
+                    .last()
+                    .expect("diagnostics always refer to an existing line");
+                let diagnostic_status = ExitStatus::of(&diagnostic);
+                if stderr.emit(diagnostic, location, &sources, format).is_err() {
+                    // Nothing can be reported once writing fails
+                    return status.and(ExitStatus::IoError);
+                }
+                status = status.and(diagnostic_status);
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("Would reformat {}", script.display());
+            status = status.and(ExitStatus::Error);
+        } else if let Err(e) = fs::write(&script, formatted) {
+            eprintln!("Could not write {}:\n{:?}", script.display(), e);
+            status = status.and(ExitStatus::IoError);
+        }
+    }
+    status
+}
+
+/// Apply the suggested fixes to all scripts in the given paths.
+/// Fails if a script could not be read or written.
+fn fix_scripts(paths: &[PathBuf], cli_levels: &LintLevels) -> ExitStatus {
+    let mut scripts = vec![];
+    for path in paths {
+        if let Err(e) = util::collect_scripts(path, &mut scripts) {
+            eprintln!("Could not read {}:\n{:?}", path.display(), e);
+            return ExitStatus::IoError;
+        }
+    }
+
+    let mut status = ExitStatus::Success;
+    for script in scripts {
+        let source = match fs::read_to_string(&script) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Could not read {}:\n{:?}", script.display(), e);
+                status = status.and(ExitStatus::IoError);
+                continue;
+            }
+        };
 
-            if options.interactive {
-                run_repl(&mut i, Repl::new(), Context::Repl, &mut stdout);
+        let lint_levels = match script_lint_levels(&script, cli_levels) {
+            Ok(lint_levels) => lint_levels,
+            Err(e) => {
+                status = status.and(e);
+                continue;
+            }
+        };
+
+        let (fixed, applied) = fix::fix(&source, lint_levels);
+        if applied == 0 {
+            continue;
+        }
+        match fs::write(&script, fixed) {
+            Ok(()) => println!("Fixed {} issue(s) in {}", applied, script.display()),
+            Err(e) => {
+                eprintln!("Could not write {}:\n{:?}", script.display(), e);
+                status = status.and(ExitStatus::IoError);
             }
-        } else {
-            // Enter a REPL
-            run_repl(&mut i, Repl::new(), Context::Repl, &mut stdout);
         }
     }
+    status
+}
+
+/// Parse and lint all scripts in the given paths, without answering any questions.
+/// Fails if any script contains an error (including denied warnings).
+fn check_scripts(
+    paths: &[PathBuf],
+    cli_levels: &LintLevels,
+    stderr: &mut StandardStream,
+    format: MessageFormat,
+) -> ExitStatus {
+    let mut scripts = vec![];
+    for path in paths {
+        if let Err(e) = util::collect_scripts(path, &mut scripts) {
+            eprintln!("Could not read {}:\n{:?}", path.display(), e);
+            return ExitStatus::IoError;
+        }
+    }
+
+    let mut status = ExitStatus::Success;
+    for script in scripts {
+        let lint_levels = match script_lint_levels(&script, cli_levels) {
+            Ok(lint_levels) => lint_levels,
+            Err(e) => {
+                status = status.and(e);
+                continue;
+            }
+        };
+
+        let mut i = Interpreter::new();
+        i.set_lint_levels(lint_levels);
+        i.set_check_only(true);
+        match repl::run_file(&mut i, &script, stderr, format) {
+            Ok(script_status) => status = status.and(script_status),
+            Err(e) => {
+                eprintln!("Could not read {}:\n{:?}", script.display(), e);
+                status = status.and(ExitStatus::IoError);
+            }
+        }
+    }
+    status
+}
+
+/// The lint levels for a script: those from the closest config file,
+/// overridden by the ones given on the command line
+fn script_lint_levels(script: &Path, cli_levels: &LintLevels) -> Result<LintLevels, ExitStatus> {
+    LintLevels::for_script(script, cli_levels).map_err(|(path, msg)| {
+        eprintln!("Could not load {}:\n{}", path.display(), msg);
+        ExitStatus::Error
+    })
 }
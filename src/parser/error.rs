@@ -45,24 +45,27 @@ impl<'a> From<(TokenNotFound, &'a str)> for Diagnostic<'a> {
         let annotation = if from.0.expected.len() == 1 {
             Annotation {
                 annotation_type: AnnotationType::Info,
-                span: span,
+                span,
                 msg: format!("Expected {:?}", from.0.expected[0]),
+                location: None,
             }
         } else {
             Annotation {
                 annotation_type: AnnotationType::Info,
-                span: span,
+                span,
                 msg: format!("Expected any of {:?}", from.0.expected),
+                location: None,
             }
         };
 
         Self {
-            code: None,
+            code: Some("E001"),
             buffer: from.1,
             annotation_type: AnnotationType::Error,
             annotations: vec![annotation],
             msg: "Expected token was not found".to_owned(),
             note: None,
+            suggestions: vec![],
         }
     }
 }
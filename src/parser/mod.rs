@@ -1,6 +1,7 @@
 pub mod error;
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub mod span;
 pub mod symbol;
 mod token;
-mod tokenizer;
+pub mod tokenizer;
@@ -2,9 +2,9 @@
 
 use super::error::TokenNotFound;
 use super::span::{Span, Spanned};
-use super::symbol::{Atom, Line};
+use super::symbol::{Atom, Line, Statement};
 use super::token::Token;
-use super::tokenizer::Tokenizer;
+use super::tokenizer::{self, Tokenizer};
 use crate::debug::warning::Warning;
 
 /// A data structure that creates a program from tokens
@@ -13,34 +13,85 @@ use crate::debug::warning::Warning;
 pub struct Parser<'a> {
     buffer: &'a str,
     tokenizer: Tokenizer<'a>,
+    /// Whether the buffer is the first line of a script, which may be a `#!` line
+    first_line: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(buffer: &'a str) -> Self {
         Self {
-            buffer: buffer,
+            buffer,
             tokenizer: Tokenizer::new(buffer),
+            first_line: false,
+        }
+    }
+
+    /// Create a parser for the first line of a script, which ignores a `#!` line
+    /// so that scripts can be executed directly (`#!/usr/bin/env leuchtkraft`)
+    pub fn first_line(buffer: &'a str) -> Self {
+        Self {
+            first_line: true,
+            ..Self::new(buffer)
         }
     }
 
     /// Try to parse the internal buffer as a line
-    pub fn line(
-        &self,
-        warnings: &mut Vec<Warning>,
-    ) -> Result<Option<Spanned<Line>>, TokenNotFound> {
+    pub fn line(&self, warnings: &mut Vec<Warning>) -> Result<Statement<'a>, TokenNotFound> {
+        if self.first_line && self.tokenizer.try_read(&mut 0, Token::Shebang).is_some() {
+            return Ok(Statement {
+                line: None,
+                comment: None,
+            });
+        }
+
         // Look at how nice PEG grammars look!
         let mut expected = match self.forall(0, warnings) {
-            Ok(forall_stmt) => return Ok(Some(forall_stmt)),
+            Ok((forall_stmt, comment)) => {
+                return Ok(Statement {
+                    line: Some(forall_stmt),
+                    comment,
+                })
+            }
             Err(e) => e,
         };
 
+        match self.import(0) {
+            Ok((import, comment)) => {
+                return Ok(Statement {
+                    line: Some(import),
+                    comment,
+                })
+            }
+            Err(e) => expected.join(e),
+        }
+
+        match self.module(0) {
+            Ok((module, comment)) => {
+                return Ok(Statement {
+                    line: Some(module),
+                    comment,
+                })
+            }
+            Err(e) => expected.join(e),
+        }
+
         match self.rule(0, warnings) {
-            Ok(rule) => return Ok(Some(rule)),
+            Ok((rule, comment)) => {
+                return Ok(Statement {
+                    line: Some(rule),
+                    comment,
+                })
+            }
             Err(e) => expected.join(e),
         }
 
         match self.empty(0) {
-            Ok(_) => return Ok(None),
+            Ok(comment) => {
+                return Ok(Statement {
+                    line: None,
+                    comment,
+                })
+            }
             Err(e) => expected.join(e),
         }
 
@@ -94,17 +145,27 @@ impl<'a> Parser<'a> {
     /// Skip any constructs that can always appear inbetween tokens
     /// like whitespaces
     fn skip_filler(&self, position: &mut usize) {
-        while let Some(_) = self.tokenizer.try_read(position, Token::Space) {}
+        while self.tokenizer.try_read(position, Token::Space).is_some() {}
     }
 
     /// Check whether or not the position is a valid line ending
-    fn line_end(&self, position: &mut usize) -> Result<(), TokenNotFound> {
+    /// and return the comment at the end of the line (if any)
+    fn line_end(&self, position: &mut usize) -> Result<Option<Spanned<&'a str>>, TokenNotFound> {
         // A line **can** end with a comment, but if no comment
         // is there, don't suggest one (:
-        if self.expect(position, Token::Comment).is_err() {
-            self.expect(position, Token::End)?;
+        match self.expect(position, Token::Comment) {
+            Ok(comment) => Ok(Some(self.comment_text(comment.span()))),
+            Err(_) => {
+                self.expect(position, Token::End)?;
+                Ok(None)
+            }
         }
-        Ok(())
+    }
+
+    /// Get the text of a comment token, without the leading `//`
+    fn comment_text(&self, span: Span) -> Spanned<&'a str> {
+        let text_span = Span(span.0 + 2, span.1);
+        Spanned::new(self.read_span(text_span), text_span)
     }
 
     /// Parse a line containing a forall statement, returning
@@ -113,7 +174,7 @@ impl<'a> Parser<'a> {
         &self,
         mut pos: usize,
         warnings: &mut Vec<Warning>,
-    ) -> Result<Spanned<Line>, TokenNotFound> {
+    ) -> Result<(Spanned<Line<'a>>, Option<Spanned<&'a str>>), TokenNotFound> {
         self.expect(&mut pos, Token::Forall)?;
 
         let initial_token = self.expect(&mut pos, Token::Ident)?;
@@ -131,19 +192,58 @@ impl<'a> Parser<'a> {
             let maybe_duplicate = idents.iter().find(|i| i.as_inner() == &token_str);
 
             if let Some(duplicate) = maybe_duplicate {
+                // `idents` is never empty, it contains at least the initial token
+                let previous_end = idents.last().map_or(start_pos, |i| i.span().1);
                 warnings.push(Warning::DuplicateScopedVariable {
                     ident: token_str.to_owned(),
                     first_declaration: duplicate.span(),
                     second_declaration: token.span(),
+                    removal: Span(previous_end, token.span().1),
                 });
             }
             idents.push(token.map(token_str));
         }
 
-        self.line_end(&mut pos)?;
+        let comment = self.line_end(&mut pos)?;
 
         let forall = Spanned::new(Line::Forall(idents), Span(start_pos, end_pos));
-        Ok(forall)
+        Ok((forall, comment))
+    }
+
+    /// Parse a line importing another file, like `import "taxonomy.le"`
+    fn import(
+        &self,
+        mut pos: usize,
+    ) -> Result<(Spanned<Line<'a>>, Option<Spanned<&'a str>>), TokenNotFound> {
+        let keyword = self.expect(&mut pos, Token::Import)?;
+        let string = self.expect(&mut pos, Token::String)?;
+        let comment = self.line_end(&mut pos)?;
+
+        // The path is the string without its quotes
+        let path_span = Span(string.span().0 + 1, string.span().1 - 1);
+        let path = Spanned::new(self.read_span(path_span), path_span);
+        let import = Spanned::new(Line::Import(path), Span(keyword.span().0, string.span().1));
+        Ok((import, comment))
+    }
+
+    /// Parse a line declaring the module of a file (`module geo`)
+    /// or bringing a module into scope (`use geo`)
+    fn module(
+        &self,
+        mut pos: usize,
+    ) -> Result<(Spanned<Line<'a>>, Option<Spanned<&'a str>>), TokenNotFound> {
+        let keyword = self.expect_either(&mut pos, vec![Token::Module, Token::Use])?;
+        let name = self.expect(&mut pos, Token::Ident)?;
+        let comment = self.line_end(&mut pos)?;
+
+        let span = Span(keyword.span().0, name.span().1);
+        let name = name.map(self.read_span(name.span()));
+        let line = match keyword.as_inner() {
+            Token::Module => Line::Module(name),
+            Token::Use => Line::Use(name),
+            _ => unreachable!(),
+        };
+        Ok((Spanned::new(line, span), comment))
     }
 
     /// Return a boolean indicating whether or not the line was indented
@@ -152,7 +252,7 @@ impl<'a> Parser<'a> {
         &self,
         mut pos: usize,
         warnings: &mut Vec<Warning>,
-    ) -> Result<Spanned<Line>, TokenNotFound> {
+    ) -> Result<(Spanned<Line<'a>>, Option<Spanned<&'a str>>), TokenNotFound> {
         let is_indented = self.expect(&mut pos, Token::Indent).is_ok();
         let mut is_question = false;
 
@@ -163,6 +263,7 @@ impl<'a> Parser<'a> {
 
         // Every rule can only end after having found an implication
         let mut found_implication = false;
+        let mut comment = None;
         let end = loop {
             let expected = if found_implication {
                 vec![Token::And, Token::Implication, Token::End, Token::Comment]
@@ -177,7 +278,11 @@ impl<'a> Parser<'a> {
                     found_implication = true;
                 }
                 Token::And => {}
-                Token::End | Token::Comment => break connector.span().0,
+                Token::End => break connector.span().0,
+                Token::Comment => {
+                    comment = Some(self.comment_text(connector.span()));
+                    break connector.span().0;
+                }
                 _ => unreachable!(),
             }
 
@@ -192,7 +297,7 @@ impl<'a> Parser<'a> {
             Span(start, end),
         );
 
-        Ok(rule)
+        Ok((rule, comment))
     }
 
     fn read_atom(
@@ -200,16 +305,33 @@ impl<'a> Parser<'a> {
         pos: &mut usize,
         is_question: &mut bool,
         _warnings: &mut Vec<Warning>,
-    ) -> Result<Spanned<Atom>, TokenNotFound> {
+    ) -> Result<Spanned<Atom<'a>>, TokenNotFound> {
         let found = self.expect_either(pos, vec![Token::True, Token::False, Token::Ident])?;
         match found.as_inner() {
             Token::True => Ok(found.map(Atom::True)),
             Token::False => Ok(found.map(Atom::False)),
             Token::Ident => {
-                let ident = self.read_span(found.span());
-
-                let after_ident =
-                    self.expect_either(pos, vec![Token::OpeningParen, Token::Questionmark])?;
+                // Qualified names like `geo.inside` refer to a predicate in a module
+                let mut name_span = found.span();
+                let is_qualified =
+                    *pos == name_span.1 && self.tokenizer.try_read(pos, Token::Dot).is_some();
+                if is_qualified {
+                    let name = self
+                        .tokenizer
+                        .try_read(pos, Token::Ident)
+                        .ok_or((*pos, Token::Ident))?;
+                    self.skip_filler(pos);
+                    name_span = Span(name_span.0, name.span().1);
+                }
+                let ident = self.read_span(name_span);
+
+                // Unknowns can't be qualified
+                let expected = if is_qualified {
+                    vec![Token::OpeningParen]
+                } else {
+                    vec![Token::OpeningParen, Token::Questionmark]
+                };
+                let after_ident = self.expect_either(pos, expected)?;
                 match after_ident.as_inner() {
                     Token::Questionmark => {
                         let atom = Atom::Unknown(ident);
@@ -257,12 +379,126 @@ impl<'a> Parser<'a> {
     }
 
     /// Try to read an empty line
-    fn empty(&self, mut pos: usize) -> Result<(), TokenNotFound> {
+    fn empty(&self, mut pos: usize) -> Result<Option<Spanned<&'a str>>, TokenNotFound> {
+        // Empty lines may still contain whitespace or an indented comment
+        self.skip_filler(&mut pos);
         self.line_end(&mut pos)
     }
 
     /// Read the buffer contents from a given span
-    fn read_span(&self, span: Span) -> &str {
-        &self.buffer[span.0..span.1]
+    fn read_span(&self, span: Span) -> &'a str {
+        tokenizer::slice(self.buffer, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Statement<'_> {
+        Parser::new(line)
+            .line(&mut vec![])
+            .unwrap_or_else(|_| panic!("{:?} should parse", line))
+    }
+
+    #[test]
+    fn comment() {
+        let statement = parse("true => a(x) // some comment");
+        assert!(matches!(
+            statement.line.map(Spanned::into_inner),
+            Some(Line::Rule(false, false, _))
+        ));
+        let comment = statement.comment.unwrap();
+        assert_eq!(*comment.as_inner(), " some comment");
+
+        let statement = parse("    // only a comment");
+        assert!(statement.line.is_none());
+        assert_eq!(*statement.comment.unwrap().as_inner(), " only a comment");
+    }
+
+    #[test]
+    fn non_ascii() {
+        let line = "true => größe(ä) // schön";
+        let statement = parse(line);
+        let comment = statement.comment.unwrap();
+        assert_eq!(*comment.as_inner(), " schön");
+        // Spans are character positions
+        assert_eq!(
+            comment.span().0,
+            line.chars().position(|c| c == '/').unwrap() + 2
+        );
+        assert_eq!(comment.span().1, line.chars().count());
+
+        let Some(Line::Rule(_, _, and_chains)) = statement.line.map(Spanned::into_inner) else {
+            panic!("expected a rule");
+        };
+        let Atom::Predicate(name, args) = and_chains[1][0].as_inner() else {
+            panic!("expected a predicate");
+        };
+        assert_eq!(*name, "größe");
+        assert_eq!(*args[0].as_inner(), "ä");
+    }
+
+    #[test]
+    fn shebang() {
+        let Ok(statement) = Parser::first_line("#!/usr/bin/env leuchtkraft").line(&mut vec![])
+        else {
+            panic!("a `#!` line should parse");
+        };
+        assert!(statement.line.is_none());
+        assert!(statement.comment.is_none());
+
+        // Only the first line of a script may be a `#!` line
+        assert!(Parser::new("#!/usr/bin/env leuchtkraft")
+            .line(&mut vec![])
+            .is_err());
+    }
+
+    #[test]
+    fn module_and_use() {
+        let Some(Line::Module(name)) = parse("module geo").line.map(Spanned::into_inner) else {
+            panic!("expected a module statement");
+        };
+        assert_eq!(*name.as_inner(), "geo");
+
+        let statement = parse("use geo // for inside()");
+        let Some(Line::Use(name)) = statement.line.map(Spanned::into_inner) else {
+            panic!("expected a use statement");
+        };
+        assert_eq!(*name.as_inner(), "geo");
+        assert!(statement.comment.is_some());
+
+        assert!(Parser::new("module").line(&mut vec![]).is_err());
+        assert!(Parser::new("use geo map").line(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn qualified_names() {
+        let statement = parse("true => geo.inside(x)");
+        let Some(Line::Rule(_, _, and_chains)) = statement.line.map(Spanned::into_inner) else {
+            panic!("expected a rule");
+        };
+        assert!(matches!(
+            and_chains[1][0].as_inner(),
+            Atom::Predicate("geo.inside", _)
+        ));
+    }
+
+    #[test]
+    fn import() {
+        let line = r#"import "rules/taxonomy.le" // the rules"#;
+        let statement = parse(line);
+        let Some(Line::Import(path)) = statement.line.map(Spanned::into_inner) else {
+            panic!("expected an import statement");
+        };
+        assert_eq!(*path.as_inner(), "rules/taxonomy.le");
+        // The span of the path doesn't include the quotes
+        assert_eq!(path.span().0, "import \"".len());
+        assert_eq!(*statement.comment.unwrap().as_inner(), " the rules");
+
+        assert!(Parser::new("import taxonomy.le").line(&mut vec![]).is_err());
+        assert!(Parser::new(r#"import "taxonomy.le"#)
+            .line(&mut vec![])
+            .is_err());
     }
 }
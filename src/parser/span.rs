@@ -15,10 +15,7 @@ impl Span {
 
 impl<T> Spanned<T> {
     pub fn new(content: T, span: Span) -> Self {
-        Self {
-            content: content,
-            span: span,
-        }
+        Self { content, span }
     }
 
     pub fn as_inner(&self) -> &T {
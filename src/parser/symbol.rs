@@ -2,8 +2,22 @@
 
 use super::span::Spanned;
 
+/// A single line of code along with its trailing comment
+pub struct Statement<'a> {
+    /// The code on the line, `None` if the line is empty or only contains a comment
+    pub line: Option<Spanned<Line<'a>>>,
+    /// The text of the comment, without the leading `//`
+    pub comment: Option<Spanned<&'a str>>,
+}
+
 pub enum Line<'a> {
     Forall(Vec<Spanned<&'a str>>),
+    /// The path of an imported file, without the quotes
+    Import(Spanned<&'a str>),
+    /// The module the rest of the file belongs to
+    Module(Spanned<&'a str>),
+    /// A module whose predicates can be used without qualifying them
+    Use(Spanned<&'a str>),
     /// (is_indented, is_question, and_chains)
     Rule(bool, bool, Vec<Vec<Spanned<Atom<'a>>>>),
 }
@@ -12,15 +26,13 @@ pub enum Line<'a> {
 pub enum Atom<'a> {
     True,
     False,
+    /// The name might be qualified with a module, like `geo.inside`
     Predicate(&'a str, Vec<Spanned<&'a str>>),
     Unknown(&'a str),
 }
 
 impl Atom<'_> {
     pub fn is_literal(&self) -> bool {
-        match self {
-            Atom::True | Atom::False => true,
-            _ => false,
-        }
+        matches!(self, Atom::True | Atom::False)
     }
 }
@@ -26,9 +26,24 @@ pub enum Token {
     /// A comma (`,`)
     Comma,
 
+    /// A dot (`.`), separating a module from a predicate name
+    Dot,
+
     /// Forall keyword
     Forall,
 
+    /// Import keyword
+    Import,
+
+    /// A double-quoted string (`"taxonomy.le"`), which cannot contain quotes itself
+    String,
+
+    /// Module keyword
+    Module,
+
+    /// Use keyword
+    Use,
+
     /// And keyword
     And,
 
@@ -38,9 +53,13 @@ pub enum Token {
     /// False primitive
     False,
 
-    /// Singleline comment (`//`)
+    /// Singleline comment (`//`), extending to the end of the line
     Comment,
 
+    /// A `#!` line at the very beginning of a script, naming the program that executes it.
+    /// Only matches at the start of the buffer
+    Shebang,
+
     /// Spaces (used for indentation and seperating tokens)
     Space,
 
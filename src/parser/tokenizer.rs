@@ -7,12 +7,12 @@ pub struct Tokenizer<'a> {
     buffer: &'a str,
 }
 
-const RESERVED_IDENTS: [&'static str; 4] = ["forall", "and", "true", "false"];
+const RESERVED_IDENTS: [&str; 5] = ["forall", "import", "and", "true", "false"];
 
 impl<'a> Tokenizer<'a> {
     /// Create a new Lexer from an input buffer
     pub fn new(buffer: &'a str) -> Self {
-        Self { buffer: buffer }
+        Self { buffer }
     }
 
     pub fn try_read(&'a self, pos: &mut usize, token: Token) -> Option<Spanned<Token>> {
@@ -34,11 +34,35 @@ impl<'a> Tokenizer<'a> {
             Token::Implication => self.take(pos, "=>").map(|o| o.map(token)),
             Token::Questionmark => self.take(pos, "?").map(|o| o.map(token)),
             Token::Comma => self.take(pos, ",").map(|o| o.map(token)),
+            Token::Dot => self.take(pos, ".").map(|o| o.map(token)),
             Token::Forall => self.take(pos, "forall").map(|o| o.map(token)),
+            Token::Import => self.take(pos, "import").map(|o| o.map(token)),
+            Token::String => {
+                self.take(pos, "\"")?;
+                self.consume_while(pos, |c| c != &'"');
+                match self.take(pos, "\"") {
+                    Some(_) => Some(Spanned::new(token, Span(initial_pos, *pos))),
+                    None => {
+                        *pos = initial_pos;
+                        None
+                    }
+                }
+            }
+            Token::Module => self.take(pos, "module").map(|o| o.map(token)),
+            Token::Use => self.take(pos, "use").map(|o| o.map(token)),
             Token::And => self.take(pos, "and").map(|o| o.map(token)),
             Token::True => self.take(pos, "true").map(|o| o.map(token)),
             Token::False => self.take(pos, "false").map(|o| o.map(token)),
-            Token::Comment => self.take(pos, "//").map(|o| o.map(token)),
+            Token::Comment => self.take(pos, "//").map(|o| {
+                // Comments always extend to the end of the line
+                *pos = self.len();
+                Spanned::new(token, Span(o.span().0, *pos))
+            }),
+            Token::Shebang if *pos == 0 && self.buffer.starts_with("#!") => {
+                *pos = self.len();
+                Some(Spanned::new(token, Span(0, *pos)))
+            }
+            Token::Shebang => None,
             Token::Space => self
                 .consume_if(pos, |c| c.is_whitespace())
                 .map(|o| o.map(token)),
@@ -46,7 +70,7 @@ impl<'a> Tokenizer<'a> {
                 .consume_while(pos, |c| c.is_whitespace())
                 .map(|o| o.map(token)),
             Token::End => {
-                if *pos == self.buffer.len() {
+                if *pos == self.len() {
                     Some(Spanned::new(Token::End, Span::position(*pos)))
                 } else {
                     None
@@ -64,9 +88,9 @@ impl<'a> Tokenizer<'a> {
     }
 
     /// Consume if the next character matches the given predicate.
-    fn consume_if<P: 'static>(&'a self, pos: &mut usize, predicate: P) -> Option<Spanned<char>>
+    fn consume_if<P>(&'a self, pos: &mut usize, predicate: P) -> Option<Spanned<char>>
     where
-        P: FnOnce(&char) -> bool,
+        P: FnOnce(&char) -> bool + 'static,
     {
         let initial_pos = *pos;
         let c = self.buffer.chars().skip(*pos).nth(0);
@@ -84,26 +108,29 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    /// Return a single character and advance the reader position
-    ///
-    /// # Panic
-    /// Panics if the end position is outside the buffer range
+    /// Return the next n characters and advance the reader position
     fn consume_exact(&'a self, pos: &mut usize, n: usize) -> Option<Spanned<&'a str>> {
         let end = *pos + n;
-        if self.buffer.len() < n {
+        if self.len() < end {
             None
         } else {
-            let res = Spanned::new(&self.buffer[*pos..end], Span(*pos, end));
+            let span = Span(*pos, end);
+            let res = Spanned::new(slice(self.buffer, span), span);
             *pos += n;
             Some(res)
         }
     }
 
+    /// The length of the buffer in characters, like all reader positions
+    fn len(&self) -> usize {
+        self.buffer.chars().count()
+    }
+
     /// Read as long as the read character satisfies the given predicate
     /// and advance the reader position accordingly
-    fn consume_while<P: 'static>(&self, pos: &mut usize, predicate: P) -> Option<Spanned<&str>>
+    fn consume_while<P>(&self, pos: &mut usize, predicate: P) -> Option<Spanned<&str>>
     where
-        P: FnMut(&char) -> bool,
+        P: FnMut(&char) -> bool + 'static,
     {
         let len = self.buffer.chars().skip(*pos).take_while(predicate).count();
         if len == 0 {
@@ -136,3 +163,14 @@ impl<'a> Tokenizer<'a> {
         Some(Spanned::new((), Span(initial_pos, *pos)))
     }
 }
+
+/// Return the text covered by a span, whose positions are character indices
+pub fn slice(buffer: &str, span: Span) -> &str {
+    let byte_offset = |pos| {
+        buffer
+            .char_indices()
+            .nth(pos)
+            .map_or(buffer.len(), |(offset, _)| offset)
+    };
+    &buffer[byte_offset(span.0)..byte_offset(span.1)]
+}
@@ -0,0 +1,193 @@
+//! Commands for the repl itself, like `:load file.le`.
+//! They are handled before a line reaches the interpreter.
+
+use super::run_file;
+use crate::diagnostics::{AnnotationType, Diagnostic, DisplayDiagnostic, Location, MessageFormat};
+use crate::interpreter::{AnswerMode, Interpreter};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const HELP: &str = "\
+:load <file>              Execute a script in the current session
+:save <file>              Write the clauses entered so far to a file
+:list [predicate]         Show the known clauses (only those mentioning the predicate)
+:reset                    Forget all known clauses
+:scope                    Show the variables freed by the current forall block
+:models [n] [predicate..] Show up to n worlds consistent with the last question
+:mode [independent|joint] Show or change how questions with several unknowns are answered
+:help                     Show this message
+quit                      Exit the shell (or press Ctrl-D)";
+
+/// Execute a repl command, like `:models 4 canfly`, found on the given line.
+/// Diagnostics (from loaded files or the command itself) are written to the given writer,
+/// failing if that is not possible.
+pub fn run_command<W: termcolor::WriteColor>(
+    i: &mut Interpreter,
+    command: &str,
+    line: &str,
+    location: Location,
+    writer: &mut W,
+    format: MessageFormat,
+) -> io::Result<()> {
+    let (name, argument) = command
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or((command.trim(), ""));
+    let argument = argument.trim();
+    let mut words = argument.split_whitespace();
+    match name {
+        "load" if !argument.is_empty() => {
+            let path = PathBuf::from(argument);
+            match run_file(i, &path, writer, format) {
+                Ok(status) if status.is_success() => println!("=> loaded {}", path.display()),
+                Ok(_) => {}
+                Err(e) => println!("=> could not read {}: {}", path.display(), e),
+            }
+        }
+        "save" if !argument.is_empty() => {
+            let mut program = i.accepted_lines().join("\n");
+            program.push('\n');
+            match fs::write(argument, program) {
+                Ok(()) => println!(
+                    "=> saved {} line(s) to {}",
+                    i.accepted_lines().len(),
+                    argument
+                ),
+                Err(e) => println!("=> could not write {}: {}", argument, e),
+            }
+        }
+        "list" => {
+            let clauses = i.list_clauses(words.next());
+            if clauses.is_empty() {
+                println!("=> no clauses");
+            }
+            for clause in clauses {
+                println!("{}", clause);
+            }
+        }
+        "scope" => match i.scope() {
+            Some((vars, location)) => {
+                println!("=> forall {} (line {})", vars.join(", "), location.line)
+            }
+            None => println!("=> not inside a forall block"),
+        },
+        "reset" => {
+            i.reset();
+            println!("=> all clauses have been forgotten");
+        }
+        "models" => {
+            // `:models [limit] [predicate...]`
+            let mut limit = 2;
+            let mut projection = vec![];
+            for word in words {
+                match word.parse::<usize>() {
+                    Ok(n) => limit = n,
+                    Err(_) => projection.push(word),
+                }
+            }
+
+            match i.models(limit, &projection) {
+                Ok(worlds) => {
+                    for world in worlds {
+                        println!("=> {}", world);
+                    }
+                }
+                Err(msg) => {
+                    let diagnostic = Diagnostic {
+                        code: None,
+                        buffer: line,
+                        annotation_type: AnnotationType::Error,
+                        annotations: vec![],
+                        msg,
+                        note: None,
+                        suggestions: vec![],
+                    };
+                    writer.emit(diagnostic, location, i.sources(), format)?;
+                }
+            }
+        }
+        "mode" => {
+            // `:mode [independent|joint]`
+            match words.next() {
+                Some("independent") => i.set_answer_mode(AnswerMode::Independent),
+                Some("joint") => i.set_answer_mode(AnswerMode::Joint),
+                Some(other) => println!("=> unknown answer mode {:?}", other),
+                None => {}
+            }
+            let mode = match i.answer_mode() {
+                AnswerMode::Independent => "independent",
+                AnswerMode::Joint => "joint",
+            };
+            println!("=> answer mode is {}", mode);
+        }
+        "help" => println!("{}", HELP),
+        "load" | "save" => println!("=> usage: :{} <file>", name),
+        _ => println!("=> unknown command {:?}, see :help", command),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{run_repl, Context};
+    use super::*;
+    use termcolor::Buffer;
+
+    /// Type the given lines into a repl session
+    fn type_lines(i: &mut Interpreter, lines: &[String]) -> String {
+        let mut writer = Buffer::no_color();
+        run_repl(
+            i,
+            lines.iter().cloned(),
+            Context::Repl,
+            &mut writer,
+            MessageFormat::Human,
+        );
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn save_and_reload() {
+        let dir = std::env::temp_dir().join("leuchtkraft_save_and_reload");
+        fs::create_dir_all(&dir).unwrap();
+        let cities = dir.join("cities.le");
+        let saved = dir.join("saved.le");
+        fs::write(&cities, "module cities\ntrue => city(berlin)\n").unwrap();
+
+        let mut i = Interpreter::new();
+        let import = format!("import \"{}\"", cities.display());
+        let diagnostics = type_lines(
+            &mut i,
+            &[
+                import.clone(),
+                "module geo".to_owned(),
+                "use cities".to_owned(),
+                "true => inside(berlin)".to_owned(),
+                format!(":save {}", saved.display()),
+            ],
+        );
+        assert!(diagnostics.is_empty(), "{}", diagnostics);
+
+        // The imported file is kept as its import statement, not copied
+        let program = fs::read_to_string(&saved).unwrap();
+        assert_eq!(
+            program,
+            format!(
+                "{}\nmodule geo\nuse cities\ntrue => inside(berlin)\n",
+                import
+            )
+        );
+
+        let mut reloaded = Interpreter::new();
+        let status = run_file(
+            &mut reloaded,
+            &saved,
+            &mut Buffer::no_color(),
+            MessageFormat::Human,
+        );
+        assert_eq!(status.unwrap(), crate::exit::ExitStatus::Success);
+        assert_eq!(reloaded.list_clauses(None), i.list_clauses(None));
+        assert_eq!(reloaded.list_clauses(Some("geo.inside")).len(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+//! Candidates for completing the word in front of the cursor with Tab
+
+use crate::interpreter::Interpreter;
+
+const KEYWORDS: [&str; 7] = ["forall", "import", "module", "use", "and", "true", "false"];
+
+/// A word the user might be typing
+pub struct Completion {
+    /// The text that replaces the word in front of the cursor
+    pub text: String,
+    /// How the completion is listed when there are several candidates
+    pub display: String,
+}
+
+/// The keywords, the predicates (with their arity) and the constants
+/// the interpreter has seen so far
+pub fn candidates(i: &Interpreter) -> Vec<Completion> {
+    let keywords = KEYWORDS.iter().map(|keyword| Completion {
+        text: keyword.to_string(),
+        display: keyword.to_string(),
+    });
+    let predicates = i.symbols().predicates().map(|(name, arity)| Completion {
+        text: format!("{}(", name),
+        display: format!("{}/{}", name, arity),
+    });
+    let constants = i.symbols().constants().map(|name| Completion {
+        text: name.to_owned(),
+        display: name.to_owned(),
+    });
+    keywords.chain(predicates).chain(constants).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repl::Context;
+
+    /// The candidates after executing the given lines
+    fn candidates_after(lines: &[&str]) -> Vec<Completion> {
+        let mut i = Interpreter::new();
+        let source = i.add_source(Context::Repl);
+        for line in lines {
+            let location = i.push_line(source, line);
+            assert!(i.execute(line, location, &mut vec![]).is_ok(), "{}", line);
+        }
+        candidates(&i)
+    }
+
+    /// How the candidates are listed after executing the given lines
+    fn displayed(lines: &[&str]) -> Vec<String> {
+        candidates_after(lines)
+            .into_iter()
+            .map(|c| c.display)
+            .collect()
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(displayed(&[]), KEYWORDS);
+    }
+
+    #[test]
+    fn predicates_and_constants() {
+        let displayed = displayed(&["true => bird(tweety)", "bird(tweety) => owns(mary, tweety)"]);
+        assert_eq!(displayed[..KEYWORDS.len()], KEYWORDS);
+        let rest = &displayed[KEYWORDS.len()..];
+        assert!(rest.contains(&"bird/1".to_owned()), "{:?}", rest);
+        assert!(rest.contains(&"owns/2".to_owned()), "{:?}", rest);
+        assert!(rest.contains(&"tweety".to_owned()), "{:?}", rest);
+        assert!(rest.contains(&"mary".to_owned()), "{:?}", rest);
+        assert_eq!(rest.len(), 4, "{:?}", rest);
+
+        // Predicates are completed up to their opening parenthesis
+        let texts: Vec<String> = candidates_after(&["true => größe(ä)"])
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        assert!(texts.contains(&"größe(".to_owned()), "{:?}", texts);
+        assert!(texts.contains(&"ä".to_owned()), "{:?}", texts);
+    }
+}
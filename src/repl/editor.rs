@@ -0,0 +1,495 @@
+//! A minimal line editor for the repl, supporting cursor movement,
+//! history navigation, reverse history search (Ctrl-R) and completion (Tab).
+//! If stdin or stdout is not a terminal, lines are read as they are
+//! (without showing a prompt, if stdin is not a terminal).
+
+use super::completion::Completion;
+use super::history::History;
+use super::terminal::{self, RawMode};
+use std::io::{self, BufRead, Read, Write};
+
+/// Typing tab at the start of a word inserts this many spaces
+const TAB_WIDTH: usize = 4;
+
+/// The result of reading a line
+pub enum Input {
+    Line(String),
+    /// The user pressed Ctrl-C
+    Interrupted,
+    /// The input ended, or the user pressed Ctrl-D on an empty line
+    Eof,
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// A control character like Ctrl-R, as its (lowercase) letter
+    Ctrl(char),
+    /// A key or escape sequence that isn't supported
+    Unknown,
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buffer = [0];
+    loop {
+        match input.read(&mut buffer) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buffer[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read a single key press, returning `None` at the end of the input
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape_sequence(input)?,
+        0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+        0x00..=0x1f => Key::Unknown,
+        _ => read_char(input, byte)?,
+    };
+    Ok(Some(key))
+}
+
+/// Decode a (possibly multi-byte) UTF-8 character, given its first byte
+fn read_char(input: &mut impl Read, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|c| c.chars().next())
+        .map_or(Key::Unknown, Key::Char))
+}
+
+/// Read the rest of an escape sequence like `ESC [ A` (the up arrow)
+fn read_escape_sequence(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => {}
+        _ => return Ok(Key::Unknown),
+    }
+
+    // Parameters are digits and semicolons, the final byte determines the key
+    let mut params = String::new();
+    let last = loop {
+        match read_byte(input)? {
+            Some(byte @ (b'0'..=b'9' | b';')) => params.push(byte as char),
+            Some(byte) => break byte,
+            None => return Ok(Key::Unknown),
+        }
+    };
+    Ok(match (last, params.as_str()) {
+        (b'A', _) => Key::Up,
+        (b'B', _) => Key::Down,
+        (b'C', _) => Key::Right,
+        (b'D', _) => Key::Left,
+        (b'H', _) | (b'~', "1") | (b'~', "7") => Key::Home,
+        (b'F', _) | (b'~', "4") | (b'~', "8") => Key::End,
+        (b'~', "3") => Key::Delete,
+        _ => Key::Unknown,
+    })
+}
+
+/// The line that is currently being edited
+#[derive(Default)]
+struct Line {
+    chars: Vec<char>,
+    /// Position of the cursor, in chars
+    cursor: usize,
+}
+
+impl Line {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        Line {
+            cursor: chars.len(),
+            chars,
+        }
+    }
+
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Delete the word before the cursor, including any whitespace after it
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Where the (possibly qualified) identifier in front of the cursor starts
+    fn word_start(&self) -> usize {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+        let mut start = self.cursor;
+        while start > 0 && is_word_char(self.chars[start - 1]) {
+            start -= 1;
+        }
+        start
+    }
+}
+
+/// Redraw the prompt and the line, then move the terminal cursor to the right position
+fn redraw(out: &mut impl Write, prompt: &str, line: &Line) -> io::Result<()> {
+    write!(out, "\r{}{}\x1b[K", prompt, line.text())?;
+    let back = line.chars.len() - line.cursor;
+    if back != 0 {
+        write!(out, "\x1b[{}D", back)?;
+    }
+    out.flush()
+}
+
+/// How a reverse history search ended
+enum Search {
+    /// Continue editing the found entry
+    Edit(String),
+    /// Submit the found entry right away
+    Submit(String),
+    /// Go back to the line as it was before the search
+    Cancel,
+    Interrupted,
+    Eof,
+}
+
+pub struct LineEditor {
+    history: History,
+}
+
+impl LineEditor {
+    pub fn new(history: History) -> Self {
+        Self { history }
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        self.history.add(line);
+    }
+
+    /// Read a line, showing the given prompt. When editing, the line starts out as `initial`,
+    /// and pressing Tab completes the word in front of the cursor to one of the `completions`.
+    pub fn read_line(&mut self, prompt: &str, initial: &str, completions: &[Completion]) -> Input {
+        let result = match terminal::is_interactive().then(RawMode::enable).flatten() {
+            Some(_raw_mode) => self.edit(
+                &mut io::stdin().lock(),
+                &mut io::stdout().lock(),
+                prompt,
+                initial,
+                completions,
+            ),
+            None => read_plain(prompt),
+        };
+        result.unwrap_or_else(|e| {
+            eprintln!("Cannot read from stdin: {}", e);
+            Input::Eof
+        })
+    }
+
+    /// Edit a line, reading key presses from `input` and drawing it to `out`
+    fn edit(
+        &mut self,
+        input: &mut impl Read,
+        out: &mut impl Write,
+        prompt: &str,
+        initial: &str,
+        completions: &[Completion],
+    ) -> io::Result<Input> {
+        let mut line = Line::new(initial);
+        // The history entry that is shown, `history.len()` for the line being typed
+        let mut history_ix = self.history.len();
+        // The line being typed, while an older entry is shown
+        let mut draft = String::new();
+
+        redraw(out, prompt, &line)?;
+        loop {
+            let key = match read_key(input)? {
+                Some(key) => key,
+                None => return Ok(Input::Eof),
+            };
+            match key {
+                Key::Enter => {
+                    write!(out, "\r\n")?;
+                    return Ok(Input::Line(line.text()));
+                }
+                Key::Ctrl('c') => {
+                    write!(out, "^C\r\n")?;
+                    return Ok(Input::Interrupted);
+                }
+                Key::Ctrl('d') if line.chars.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(Input::Eof);
+                }
+                Key::Ctrl('d') | Key::Delete => line.delete(),
+                Key::Backspace => line.backspace(),
+                Key::Ctrl('w') => line.delete_word(),
+                Key::Ctrl('u') => {
+                    line.chars.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::Ctrl('k') => line.chars.truncate(line.cursor),
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => {
+                    line.cursor = (line.cursor + 1).min(line.chars.len())
+                }
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.chars.len(),
+                Key::Up | Key::Ctrl('p') if history_ix > 0 => {
+                    if history_ix == self.history.len() {
+                        draft = line.text();
+                    }
+                    history_ix -= 1;
+                    line = Line::new(self.history.get(history_ix).unwrap_or_default());
+                }
+                Key::Down | Key::Ctrl('n') if history_ix < self.history.len() => {
+                    history_ix += 1;
+                    line = Line::new(self.history.get(history_ix).unwrap_or(&draft));
+                }
+                Key::Ctrl('r') => match self.search(input, out, &line.text())? {
+                    Search::Edit(text) => line = Line::new(&text),
+                    Search::Submit(text) => {
+                        write!(out, "\r{}{}\x1b[K\r\n", prompt, text)?;
+                        return Ok(Input::Line(text));
+                    }
+                    Search::Cancel => {}
+                    Search::Interrupted => {
+                        write!(out, "^C\r\n")?;
+                        return Ok(Input::Interrupted);
+                    }
+                    Search::Eof => return Ok(Input::Eof),
+                },
+                Key::Tab => complete(out, &mut line, completions)?,
+                Key::Char(c) => line.insert(c),
+                _ => {}
+            }
+            redraw(out, prompt, &line)?;
+        }
+    }
+
+    /// Search the history for entries containing the typed text, most recent first.
+    /// Pressing Ctrl-R again finds the next older match.
+    fn search(
+        &self,
+        input: &mut impl Read,
+        out: &mut impl Write,
+        original: &str,
+    ) -> io::Result<Search> {
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+        let mut failed = false;
+        loop {
+            let shown = found
+                .and_then(|ix| self.history.get(ix))
+                .unwrap_or(original)
+                .to_owned();
+            let status = if failed { "failed " } else { "" };
+            write!(
+                out,
+                "\r({}reverse-i-search)`{}': {}\x1b[K",
+                status, query, shown
+            )?;
+            out.flush()?;
+
+            let key = match read_key(input)? {
+                Some(key) => key,
+                None => return Ok(Search::Eof),
+            };
+            let before = match key {
+                Key::Char(c) => {
+                    query.push(c);
+                    // The current match might still match
+                    found.map_or(self.history.len(), |ix| ix + 1)
+                }
+                Key::Backspace => {
+                    query.pop();
+                    self.history.len()
+                }
+                Key::Ctrl('r') => found.unwrap_or(self.history.len()),
+                Key::Enter => return Ok(Search::Submit(shown)),
+                Key::Ctrl('g') => return Ok(Search::Cancel),
+                Key::Ctrl('c') => return Ok(Search::Interrupted),
+                _ => return Ok(Search::Edit(shown)),
+            };
+            match self.history.search(&query, before) {
+                Some(ix) => {
+                    found = Some(ix);
+                    failed = false;
+                }
+                None => failed = true,
+            }
+        }
+    }
+}
+
+/// Complete the word in front of the cursor as far as all matching completions agree.
+/// If that doesn't add anything, the matches are listed below the line.
+/// Without a word in front of the cursor, the line is indented instead.
+fn complete(out: &mut impl Write, line: &mut Line, completions: &[Completion]) -> io::Result<()> {
+    let start = line.word_start();
+    if start == line.cursor {
+        (0..TAB_WIDTH).for_each(|_| line.insert(' '));
+        return Ok(());
+    }
+    let word: String = line.chars[start..line.cursor].iter().collect();
+    let matches: Vec<&Completion> = completions
+        .iter()
+        .filter(|completion| completion.text.starts_with(&word))
+        .collect();
+
+    let common = match matches.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.text.as_str(), |common, completion| {
+            let len = common
+                .char_indices()
+                .zip(completion.text.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(completion.text.len()), |((ix, _), _)| ix);
+            &common[..len]
+        }),
+        // Nothing to complete
+        None => return Ok(()),
+    };
+
+    if common.len() > word.len() {
+        common[word.len()..].chars().for_each(|c| line.insert(c));
+    } else {
+        let mut shown: Vec<&str> = matches.iter().map(|m| m.display.as_str()).collect();
+        shown.dedup();
+        // A single match is already complete
+        if shown.len() < 2 {
+            return Ok(());
+        }
+        write!(out, "\r\n{}\r\n", shown.join("  "))?;
+    }
+    Ok(())
+}
+
+/// Read a line without any editing support, like when the input is piped in
+fn read_plain(prompt: &str) -> io::Result<Input> {
+    // Nobody is there to read the prompt if the input doesn't come from a terminal
+    if terminal::is_input_terminal() {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+    }
+
+    let mut buffer = String::new();
+    if io::stdin().lock().read_line(&mut buffer)? == 0 {
+        return Ok(Input::Eof);
+    }
+    // The last line of the input might not end with a newline
+    if buffer.ends_with('\n') {
+        buffer.pop();
+        if buffer.ends_with('\r') {
+            buffer.pop();
+        }
+    }
+    Ok(Input::Line(buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: &str = "\x1b[D";
+    const RIGHT: &str = "\x1b[C";
+    const HOME: &str = "\x1b[H";
+    const DELETE: &str = "\x1b[3~";
+    const BACKSPACE: &str = "\x7f";
+
+    /// Type the given keys into an empty line, returning the entered line and what was drawn
+    fn type_keys(editor: &mut LineEditor, keys: &str) -> (String, String) {
+        let mut out = vec![];
+        let input = editor
+            .edit(&mut keys.as_bytes(), &mut out, "> ", "", &[])
+            .unwrap();
+        let Input::Line(line) = input else {
+            panic!("{:?} should enter a line", keys);
+        };
+        (line, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let mut editor = LineEditor::new(History::default());
+        let keys = format!("größe{}{}x\r", LEFT, LEFT);
+        let (line, out) = type_keys(&mut editor, &keys);
+        assert_eq!(line, "gröxße");
+        // The terminal cursor moves back by characters, not bytes
+        assert!(out.ends_with("gröxße\x1b[K\x1b[2D\r\n"), "{:?}", out);
+
+        let keys = format!("äöü{}{}{}{}{}\r", HOME, DELETE, RIGHT, BACKSPACE, "a");
+        assert_eq!(type_keys(&mut editor, &keys).0, "aü");
+
+        // The cursor stays inside the line
+        let keys = format!("𝕏{}{}a{}{}b\r", LEFT, LEFT, RIGHT, RIGHT);
+        assert_eq!(type_keys(&mut editor, &keys).0, "a𝕏b");
+    }
+
+    #[test]
+    fn delete_word() {
+        let mut editor = LineEditor::new(History::default());
+        let keys = "true => größe(ä)  \x17x\r";
+        assert_eq!(type_keys(&mut editor, keys).0, "true => x");
+        let keys = "a(ä) => größe(ä)\x1b[D\x1b[D\x17\x17\r";
+        assert_eq!(type_keys(&mut editor, keys).0, "a(ä) ä)");
+    }
+
+    #[test]
+    fn history() {
+        let mut editor = LineEditor::new(History::default());
+        editor.add_history("true => größe(ä)");
+        editor.add_history("true => a(x)");
+        let keys = "\x1b[A\x1b[A\x1b[D\x7fe\r";
+        assert_eq!(type_keys(&mut editor, keys).0, "true => größe(e)");
+
+        // Ctrl-R searches the history, Enter submits the match
+        assert_eq!(type_keys(&mut editor, "\x12ß\r").0, "true => größe(ä)");
+    }
+}
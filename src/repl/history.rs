@@ -0,0 +1,150 @@
+//! Lines entered into the repl, saved across sessions in `~/.leuchtkraft_history`
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = ".leuchtkraft_history";
+
+/// Older entries are dropped once the history grows beyond this
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Where new entries are saved, `None` if there is no home directory
+    /// or the lines are not typed by the user
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Load the history file from the user's home directory.
+    /// A missing or unreadable file results in an empty history.
+    pub fn load() -> Self {
+        match env::var_os("HOME") {
+            Some(home) => History::open(PathBuf::from(home).join(FILE_NAME)),
+            None => History::default(),
+        }
+    }
+
+    /// Load the history from the given file, keeping only the newest entries.
+    /// A missing or unreadable file results in an empty history.
+    pub fn open(path: PathBuf) -> Self {
+        let mut entries: Vec<String> = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_owned)
+            .collect();
+
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+            let mut contents = entries.join("\n");
+            contents.push('\n');
+            // Losing old entries is not worth bothering the user about
+            let _ = fs::write(&path, contents);
+        }
+
+        History {
+            entries,
+            path: Some(path),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, ix: usize) -> Option<&str> {
+        self.entries.get(ix).map(String::as_str)
+    }
+
+    /// Remember a line and append it to the history file.
+    /// Empty lines and repetitions of the previous line are skipped.
+    pub fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_owned());
+
+        if let Some(path) = &self.path {
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            if let Ok(mut file) = file {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    /// Find the most recent entry before `before` that contains `query`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A history file in the temporary directory, removed before use
+    fn history_file(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("leuchtkraft_history_{}", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn add() {
+        let path = history_file("add");
+        let mut history = History::open(path.clone());
+        assert_eq!(history.len(), 0);
+
+        history.add("true => a(x)");
+        history.add("true => a(x)");
+        history.add("   ");
+        history.add("X? => a(x) => X?");
+        history.add("true => a(x)");
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(2), Some("true => a(x)"));
+        assert_eq!(history.get(3), None);
+
+        // Entries are kept across sessions
+        let history = History::open(path);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(1), Some("X? => a(x) => X?"));
+    }
+
+    #[test]
+    fn search() {
+        let mut history = History::default();
+        for line in [
+            "true => bird(tweety)",
+            "true => canfly(tweety)",
+            "true => bird(jane)",
+        ] {
+            history.add(line);
+        }
+        assert_eq!(history.search("bird", 3), Some(2));
+        assert_eq!(history.search("bird", 2), Some(0));
+        assert_eq!(history.search("bird", 0), None);
+        assert_eq!(history.search("tweety", 100), Some(1));
+        assert_eq!(history.search("penguin", 3), None);
+        assert_eq!(history.search("", 3), Some(2));
+    }
+
+    #[test]
+    fn truncation() {
+        let path = history_file("truncation");
+        let lines: Vec<String> = (0..MAX_ENTRIES + 10).map(|n| format!("a({})", n)).collect();
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let history = History::open(path.clone());
+        assert_eq!(history.len(), MAX_ENTRIES);
+        assert_eq!(history.get(0), Some("a(10)"));
+        // The file itself is truncated as well
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), MAX_ENTRIES);
+        assert_eq!(contents.lines().next(), Some("a(10)"));
+    }
+}
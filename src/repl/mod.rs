@@ -0,0 +1,583 @@
+mod commands;
+mod completion;
+mod editor;
+mod history;
+mod terminal;
+
+use crate::debug::error::Error;
+use crate::debug::warning::Warning;
+use crate::diagnostics::{
+    expand_tabs, AnnotationType, Diagnostic, DisplayDiagnostic, Location, MessageFormat,
+};
+use crate::exit::ExitStatus;
+use crate::interpreter::Interpreter;
+use crate::logic::UnknownValue;
+use crate::parser::span::Spanned;
+use editor::{Input, LineEditor};
+use history::History;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where [run_repl] gets the lines to execute from
+pub trait LineSource {
+    /// Get the next line, `None` once there are no more.
+    /// The interpreter is passed along so that interactive sources can offer completions.
+    fn next_line(&mut self, i: &Interpreter) -> Option<String>;
+}
+
+impl<I: Iterator<Item = String>> LineSource for I {
+    fn next_line(&mut self, _: &Interpreter) -> Option<String> {
+        self.next()
+    }
+}
+
+/// Lines typed by the user
+pub struct Repl {
+    editor: LineEditor,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        // Stay quiet when a program is piped in, and keep it out of the history
+        let history = if terminal::is_input_terminal() {
+            println!("Leuchtkraft version {}", env!("CARGO_PKG_VERSION"));
+            println!("Type 'quit' or press Ctrl-D to exit the shell");
+            History::load()
+        } else {
+            History::default()
+        };
+        Repl {
+            editor: LineEditor::new(history),
+        }
+    }
+}
+
+impl LineSource for Repl {
+    fn next_line(&mut self, i: &Interpreter) -> Option<String> {
+        let completions = completion::candidates(i);
+        // Lines inside a forall block are indented automatically
+        let (prompt, indent) = if i.inside_scopeblock() {
+            ("... ", "    ")
+        } else {
+            ("> ", "")
+        };
+        loop {
+            match self.editor.read_line(prompt, indent, &completions) {
+                Input::Line(line) if line == "quit" => return None,
+                Input::Line(line) => {
+                    self.editor.add_history(&line);
+                    return Some(line);
+                }
+                // Ctrl-C only discards the current input
+                Input::Interrupted => continue,
+                Input::Eof => return None,
+            }
+        }
+    }
+}
+
+/// What [run_repl] does with the lines that were executed without errors
+pub trait LineHandler {
+    /// Handle a line along with its answer, if it asked a question.
+    /// Diagnostics are written to the given writer.
+    fn executed<W: termcolor::WriteColor>(
+        &mut self,
+        i: &mut Interpreter,
+        line: &str,
+        location: Location,
+        answer: Option<String>,
+        writer: &mut W,
+        format: MessageFormat,
+    );
+}
+
+/// Prints the answers to stdout
+pub struct PrintAnswers;
+
+impl LineHandler for PrintAnswers {
+    fn executed<W: termcolor::WriteColor>(
+        &mut self,
+        _: &mut Interpreter,
+        _: &str,
+        _: Location,
+        answer: Option<String>,
+        _: &mut W,
+        _: MessageFormat,
+    ) {
+        if let Some(text) = answer {
+            println!("=> {}", text);
+        }
+    }
+}
+
+/// The source of code
+pub enum Context {
+    /// User inputted code line by line
+    Repl,
+
+    /// Code read from a file with provided filename
+    File(std::path::PathBuf),
+
+    /// Code passed on the command line with `--query`
+    Query,
+
+    /// A program piped into stdin, given as `-` on the command line
+    Stdin,
+}
+
+impl Context {
+    /// Determine whether or not the repl should exit after an error is encountered
+    pub fn exit_on_error(&self) -> bool {
+        match self {
+            Context::Repl | Context::Query => false,
+            Context::File(_) | Context::Stdin => true,
+        }
+    }
+
+    /// Whether the code is typed into the shell line by line
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Context::Repl)
+    }
+}
+
+/// Start a interactive Leuchtkraft shell.
+/// Answers are printed to stdout, diagnostics to the given writer.
+/// Returns the status caused by the first error (including denied warnings),
+/// or [ExitStatus::Contradiction] if a question could not be answered.
+pub fn run_repl<I, W>(
+    i: &mut Interpreter,
+    source: I,
+    ctx: Context,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus
+where
+    I: LineSource,
+    W: termcolor::WriteColor,
+{
+    run_lines(i, source, ctx, &mut PrintAnswers, writer, format)
+}
+
+/// Like [run_repl], but the answers are passed to the given handler
+fn run_lines<I, H, W>(
+    i: &mut Interpreter,
+    mut source: I,
+    ctx: Context,
+    handler: &mut H,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus
+where
+    I: LineSource,
+    H: LineHandler,
+    W: termcolor::WriteColor,
+{
+    let mut status = ExitStatus::Success;
+    // Nothing is evaluated when only checking, so later lines can still be checked after an error
+    let exit_on_error = ctx.exit_on_error() && !i.is_check_only();
+    let interactive = ctx.is_interactive();
+    let source_id = i.add_source(ctx);
+    let mut last_location = None;
+    while let Some(mut line) = source.next_line(i) {
+        let mut warnings = vec![];
+
+        // Tabs only mess up formatting later, but the source map keeps the original line too
+        let location = i.push_line(source_id, &line);
+        line = expand_tabs(&line);
+        last_location = Some(location);
+
+        // Lines starting with a colon are commands for the shell itself,
+        // scripts can't use them (and get a syntax error instead)
+        if let Some(command) = line.trim_start().strip_prefix(':').filter(|_| interactive) {
+            if commands::run_command(i, command, &line, location, writer, format).is_err() {
+                // Nothing can be reported once writing fails
+                return status.and(ExitStatus::IoError);
+            }
+            continue;
+        }
+
+        // In the shell, an empty line ends the current forall block
+        if interactive && line.trim().is_empty() && i.inside_scopeblock() {
+            i.close_block(&mut warnings);
+            let warning_status = emit_warnings(i, &warnings, &line, location, writer, format);
+            status = status.and(warning_status);
+            if warning_status == ExitStatus::IoError {
+                return status;
+            }
+            continue;
+        }
+
+        let result = i.execute(&line, location, &mut warnings);
+
+        let warning_status = emit_warnings(i, &warnings, &line, location, writer, format);
+        status = status.and(warning_status);
+        if (exit_on_error && !warning_status.is_success()) || warning_status == ExitStatus::IoError
+        {
+            return status;
+        }
+
+        // Handle either the result or the errors that occured
+        match result {
+            Ok(answer) => handler.executed(i, &line, location, answer, writer, format),
+            Err(error) => {
+                let error_status = emit_error(i, error, location, writer, format);
+                status = status.and(error_status);
+                if exit_on_error || error_status == ExitStatus::IoError {
+                    return status;
+                }
+            }
+        }
+
+        if let Some(import) = i.take_import() {
+            let import_status = run_import(i, import, &line, location, handler, writer, format);
+            if !import_status.is_success() {
+                status = status.and(import_status);
+                // Just like in this file, contradictions don't stop the execution
+                if exit_on_error && import_status != ExitStatus::Contradiction {
+                    return status;
+                }
+            }
+        }
+    }
+
+    let mut warnings = vec![];
+    i.finish(&mut warnings);
+    // All remaining warnings refer to earlier lines
+    if let Some(location) = last_location {
+        status = status.and(emit_warnings(i, &warnings, "", location, writer, format));
+    }
+    if i.found_contradiction() {
+        status = status.and(ExitStatus::Contradiction);
+    }
+    status
+}
+
+/// Answer questions given on the command line, one after another.
+/// If `values_only` is set, answers are printed as the bare values of their unknowns
+/// (like `true either`) instead of a sentence, for consumption by other programs.
+pub fn run_queries<W: termcolor::WriteColor>(
+    i: &mut Interpreter,
+    queries: &[String],
+    values_only: bool,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus {
+    let mut status = ExitStatus::Success;
+    let source_id = i.add_source(Context::Query);
+    for query in queries {
+        let mut warnings = vec![];
+        let location = i.push_line(source_id, query);
+        let query = expand_tabs(query);
+        let result = i.execute(&query, location, &mut warnings);
+
+        status = status.and(emit_warnings(
+            i, &warnings, &query, location, writer, format,
+        ));
+        match result {
+            Ok(Some(text)) => match i.take_answer() {
+                Some(answer) if values_only => {
+                    let values: Vec<&str> =
+                        answer.iter().map(|(_, value)| value_name(value)).collect();
+                    println!("{}", values.join(" "));
+                }
+                _ => println!("=> {}", text),
+            },
+            Ok(None) => {}
+            Err(error) => status = status.and(emit_error(i, error, location, writer, format)),
+        }
+        if let Some(import) = i.take_import() {
+            status = status.and(run_import(
+                i,
+                import,
+                &query,
+                location,
+                &mut PrintAnswers,
+                writer,
+                format,
+            ));
+        }
+    }
+
+    if i.found_contradiction() {
+        status = status.and(ExitStatus::Contradiction);
+    }
+    status
+}
+
+/// How a value is written in Leuchtkraft code and `// expect:` comments
+fn value_name(value: &UnknownValue) -> &'static str {
+    match value {
+        UnknownValue::True => "true",
+        UnknownValue::False => "false",
+        UnknownValue::Either => "either",
+        UnknownValue::Neither => "neither",
+        UnknownValue::LimitExceeded(_) => "limit_exceeded",
+    }
+}
+
+/// Execute a script in the current session, `-` reads it from stdin.
+/// Fails if the script can't be read, otherwise the status of running it is returned.
+pub fn run_file<W: termcolor::WriteColor>(
+    i: &mut Interpreter,
+    path: &Path,
+    writer: &mut W,
+    format: MessageFormat,
+) -> io::Result<ExitStatus> {
+    run_file_with(i, path, &mut PrintAnswers, writer, format)
+}
+
+/// Like [run_file], but the answers (including those in imported files)
+/// are passed to the given handler
+pub fn run_file_with<H, W>(
+    i: &mut Interpreter,
+    path: &Path,
+    handler: &mut H,
+    writer: &mut W,
+    format: MessageFormat,
+) -> io::Result<ExitStatus>
+where
+    H: LineHandler,
+    W: termcolor::WriteColor,
+{
+    if path == Path::new("-") {
+        let mut program = String::new();
+        io::stdin().read_to_string(&mut program)?;
+        return Ok(run_lines(
+            i,
+            program.lines().map(str::to_owned),
+            Context::Stdin,
+            handler,
+            writer,
+            format,
+        ));
+    }
+
+    let file = fs::read_to_string(path)?;
+    // Imports are resolved relative to the script, cycles are detected using its canonical path
+    i.enter_file(fs::canonicalize(path)?);
+    let status = run_lines(
+        i,
+        file.lines().map(str::to_owned),
+        Context::File(path.to_owned()),
+        handler,
+        writer,
+        format,
+    );
+    i.leave_file();
+    Ok(status)
+}
+
+/// Execute the file named in an import statement and report it if that fails
+fn run_import<H, W>(
+    i: &mut Interpreter,
+    import: Spanned<String>,
+    line: &str,
+    location: Location,
+    handler: &mut H,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus
+where
+    H: LineHandler,
+    W: termcolor::WriteColor,
+{
+    let error = match import_file(i, import, location, handler, writer, format) {
+        Ok(status) => return status,
+        Err(error) => error,
+    };
+    emit_error(i, Diagnostic::from((error, line)), location, writer, format)
+}
+
+/// Execute the file named in an import statement, unless it was executed before.
+/// The path is relative to the directory of the importing file
+/// (or the working directory, for imports typed into the shell).
+/// Fails if the file can't be read or is still being executed,
+/// otherwise the status of running it is returned.
+pub fn import_file<H, W>(
+    i: &mut Interpreter,
+    import: Spanned<String>,
+    location: Location,
+    handler: &mut H,
+    writer: &mut W,
+    format: MessageFormat,
+) -> Result<ExitStatus, Error>
+where
+    H: LineHandler,
+    W: termcolor::WriteColor,
+{
+    let directory = match i.sources().context(location.source) {
+        Context::File(importer) => importer.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Context::Repl | Context::Query | Context::Stdin => PathBuf::new(),
+    };
+    let path = directory.join(import.as_inner());
+
+    match fs::canonicalize(&path) {
+        Ok(canonical) if i.is_loading(&canonical) => Err(Error::CyclicImport {
+            span: import.span(),
+            path: path.display().to_string(),
+        }),
+        Ok(canonical) if i.is_loaded(&canonical) => Ok(ExitStatus::Success),
+        Ok(_) => {
+            i.enter_import();
+            let status = run_file_with(i, &path, handler, writer, format);
+            i.leave_import();
+            status.map_err(|e| Error::ImportFailed {
+                span: import.span(),
+                path: path.display().to_string(),
+                reason: e.to_string(),
+            })
+        }
+        Err(e) => Err(Error::ImportFailed {
+            span: import.span(),
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/// Print the diagnostic of an error, returning the status it causes.
+/// Diagnostics that cannot be written (like when stderr was closed) cause an [ExitStatus::IoError].
+fn emit_error<W: termcolor::WriteColor>(
+    i: &Interpreter,
+    diagnostic: Diagnostic,
+    location: Location,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus {
+    let status = ExitStatus::of(&diagnostic);
+    match writer.emit(diagnostic, location, i.sources(), format) {
+        Ok(()) => status,
+        Err(_) => ExitStatus::IoError,
+    }
+}
+
+/// Print all the warnings that aren't allowed.
+/// Returns [ExitStatus::Error] if any of them were denied,
+/// or [ExitStatus::IoError] if they could not be written.
+fn emit_warnings<W: termcolor::WriteColor>(
+    i: &Interpreter,
+    warnings: &[Warning],
+    line: &str,
+    location: Location,
+    writer: &mut W,
+    format: MessageFormat,
+) -> ExitStatus {
+    let mut status = ExitStatus::Success;
+    for warning in warnings {
+        if let Some(diagnostic) = i.lint(warning, line) {
+            if matches!(diagnostic.annotation_type, AnnotationType::Error) {
+                status = ExitStatus::Error;
+            }
+            if writer
+                .emit(diagnostic, location, i.sources(), format)
+                .is_err()
+            {
+                return ExitStatus::IoError;
+            }
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termcolor::Buffer;
+
+    const BLANK_LINE_IN_BLOCK: &str = "\
+forall X
+    a(X) => b(X)
+
+    b(X) => c(X)
+true => a(x)
+c(x) => y?";
+
+    /// Run a script, returning its status and the diagnostics that were printed
+    fn run_script(i: &mut Interpreter, program: &str) -> (ExitStatus, String) {
+        let mut writer = Buffer::no_color();
+        let status = run_repl(
+            i,
+            program.lines().map(str::to_owned),
+            Context::File(PathBuf::from("test.le")),
+            &mut writer,
+            MessageFormat::Human,
+        );
+        (status, String::from_utf8(writer.into_inner()).unwrap())
+    }
+
+    #[test]
+    fn blank_line_in_block() {
+        let (status, diagnostics) = run_script(&mut Interpreter::new(), BLANK_LINE_IN_BLOCK);
+        assert_eq!(status, ExitStatus::Success, "{}", diagnostics);
+    }
+
+    #[test]
+    fn blank_line_in_block_when_checking() {
+        let mut i = Interpreter::new();
+        i.set_check_only(true);
+        let (status, diagnostics) = run_script(&mut i, BLANK_LINE_IN_BLOCK);
+        assert_eq!(status, ExitStatus::Success, "{}", diagnostics);
+    }
+
+    #[test]
+    fn commands_in_scripts() {
+        let path = std::env::temp_dir().join("leuchtkraft_commands_in_scripts.le");
+        let program = format!("true => a(x)\n:save {}", path.display());
+        let mut i = Interpreter::new();
+        i.set_check_only(true);
+        let (status, _) = run_script(&mut i, &program);
+        assert_eq!(status, ExitStatus::SyntaxError);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn checking_continues_after_errors() {
+        let mut i = Interpreter::new();
+        i.set_check_only(true);
+        let (status, diagnostics) = run_script(&mut i, "    a(x) => b(x)\n    b(x) => c(x)");
+        assert_eq!(status, ExitStatus::SyntaxError);
+        assert_eq!(diagnostics.matches("Unexpected indentation").count(), 2);
+    }
+
+    /// Fails like stderr does once the other end of a pipe was closed
+    struct ClosedPipe;
+
+    impl io::Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_writer() {
+        let run = |program: &str, ctx: Context| {
+            let mut writer = termcolor::NoColor::new(ClosedPipe);
+            let lines = program.lines().map(str::to_owned);
+            run_repl(
+                &mut Interpreter::new(),
+                lines,
+                ctx,
+                &mut writer,
+                MessageFormat::Human,
+            )
+        };
+        let file = || Context::File(PathBuf::from("test.le"));
+
+        // Errors, denied warnings and repl commands all fail to be reported
+        assert_eq!(
+            run("true => a(x\ntrue => b(x)", file()),
+            ExitStatus::IoError
+        );
+        assert_eq!(
+            run("// deny: redundant_true\ntrue and true => a(x)", file()),
+            ExitStatus::IoError
+        );
+        assert_eq!(run(":models x", Context::Repl), ExitStatus::IoError);
+        assert_eq!(run("true => a(x)", file()), ExitStatus::Success);
+    }
+}
@@ -0,0 +1,53 @@
+//! Switches the terminal into a mode where keys can be read one at a time.
+//! This uses `stty` instead of bindings to the platform's terminal API,
+//! so that no additional dependencies are needed.
+
+use std::io::{self, IsTerminal};
+use std::process::{Command, Stdio};
+
+/// Whether both stdin and stdout are connected to a terminal
+pub fn is_interactive() -> bool {
+    is_input_terminal() && io::stdout().is_terminal()
+}
+
+/// Whether stdin is connected to a terminal, as opposed to a pipe or a file
+pub fn is_input_terminal() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Run `stty` on the terminal connected to stdin, returning its output
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Restores the previous terminal settings when dropped
+pub struct RawMode {
+    /// The settings before raw mode was enabled, as printed by `stty -g`
+    saved: String,
+}
+
+impl RawMode {
+    /// Stop the terminal from buffering lines, echoing keys and turning
+    /// Ctrl-C into a signal - the line editor handles all of that instead.
+    /// Returns `None` if the terminal settings can't be changed.
+    pub fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?.trim().to_owned();
+        stty(&["-icanon", "-echo", "-isig", "-ixon", "min", "1"])?;
+        Some(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
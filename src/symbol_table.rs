@@ -0,0 +1,188 @@
+//! Keeps track of the names behind hashed identifiers so that
+//! results can be shown to the user in a readable form.
+
+use crate::logic::{Atom, Clause, Ident, Model, Var};
+use crate::util::calculate_hash;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Default)]
+pub struct SymbolTable {
+    names: HashMap<Ident, String>,
+    /// Names and arities of the predicates seen so far, for completion
+    predicates: BTreeSet<(String, usize)>,
+    /// Arguments that were not freed by a forall statement
+    constants: BTreeSet<String>,
+}
+
+pub fn str_to_ident(ident_str: &str) -> Ident {
+    Ident(calculate_hash(&ident_str))
+}
+
+impl SymbolTable {
+    /// Hash a name and remember it for later
+    pub fn intern(&mut self, name: &str) -> Ident {
+        let ident = str_to_ident(name);
+        self.names.entry(ident).or_insert_with(|| name.to_owned());
+        ident
+    }
+
+    /// Intern the name of a predicate, remembering that it takes `arity` arguments
+    pub fn intern_predicate(&mut self, name: &str, arity: usize) -> Ident {
+        self.predicates.insert((name.to_owned(), arity));
+        self.intern(name)
+    }
+
+    /// Intern a predicate argument that is not a free variable
+    pub fn intern_constant(&mut self, name: &str) -> Ident {
+        self.constants.insert(name.to_owned());
+        self.intern(name)
+    }
+
+    /// The predicates seen so far along with their arities, sorted by name
+    pub fn predicates(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.predicates
+            .iter()
+            .map(|(name, arity)| (name.as_str(), *arity))
+    }
+
+    /// Whether a predicate with this name was seen so far (with any arity)
+    pub fn is_predicate(&self, name: &str) -> bool {
+        self.predicates
+            .range((name.to_owned(), 0)..)
+            .next()
+            .is_some_and(|(predicate, _)| predicate == name)
+    }
+
+    /// The constants seen so far, sorted by name
+    pub fn constants(&self) -> impl Iterator<Item = &str> {
+        self.constants.iter().map(String::as_str)
+    }
+
+    /// Look up the original name of an ident.
+    /// Idents that were never interned are shown as their hash.
+    pub fn name(&self, ident: Ident) -> String {
+        match self.names.get(&ident) {
+            Some(name) => name.clone(),
+            None => format!("{:?}", ident),
+        }
+    }
+
+    pub fn display_ground_atom(&self, atom: &Atom<Ident>) -> String {
+        match atom {
+            Atom::Boolean(b) => b.to_string(),
+            Atom::Predicate(ident, args) => {
+                let args: Vec<String> = args.iter().map(|arg| self.name(*arg)).collect();
+                format!("{}({})", self.name(*ident), args.join(", "))
+            }
+            Atom::Unknown(ident) => format!("{}?", self.name(*ident)),
+        }
+    }
+
+    fn display_atom(&self, atom: &Atom<Var>) -> String {
+        match atom {
+            Atom::Boolean(b) => b.to_string(),
+            Atom::Predicate(ident, args) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Var::Fixed(ident) | Var::Free(ident) => self.name(*ident),
+                    })
+                    .collect();
+                format!("{}({})", self.name(*ident), args.join(", "))
+            }
+            Atom::Unknown(ident) => format!("{}?", self.name(*ident)),
+        }
+    }
+
+    /// Format a clause as Leuchtkraft code, inside a forall block
+    /// if it contains free variables
+    pub fn display_clause(&self, clause: &Clause<Var>) -> String {
+        let and_chains: Vec<String> = clause
+            .0
+            .iter()
+            .map(|and_chain| {
+                let atoms: Vec<String> = and_chain
+                    .iter()
+                    .map(|atom| self.display_atom(atom))
+                    .collect();
+                atoms.join(" and ")
+            })
+            .collect();
+        let rule = and_chains.join(" => ");
+
+        let free_vars: Vec<String> = clause
+            .free_vars()
+            .into_iter()
+            .map(|ident| self.name(ident))
+            .collect();
+        if free_vars.is_empty() {
+            rule
+        } else {
+            format!("forall {}\n    {}", free_vars.join(", "), rule)
+        }
+    }
+
+    /// Format a model as a Leuchtkraft program that can be loaded again
+    pub fn display_model(&self, model: &Model) -> String {
+        let mut program = String::new();
+        for (atom, value) in &model.0 {
+            let line = if *value {
+                format!("true => {}\n", self.display_ground_atom(atom))
+            } else {
+                format!("{} => false\n", self.display_ground_atom(atom))
+            };
+            program.push_str(&line);
+        }
+        program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning() {
+        let mut symbols = SymbolTable::default();
+        let ident = symbols.intern("größe");
+        assert!(ident == str_to_ident("größe"));
+        assert!(symbols.intern("größe") == ident);
+        assert!(symbols.intern("grösse") != ident);
+        assert_eq!(symbols.name(ident), "größe");
+
+        // Names that were never interned can't be looked up
+        let unknown = str_to_ident("tweety");
+        assert_eq!(symbols.name(unknown), format!("{:?}", unknown));
+    }
+
+    #[test]
+    fn predicates() {
+        let mut symbols = SymbolTable::default();
+        symbols.intern_predicate("owns", 2);
+        symbols.intern_predicate("bird", 1);
+        symbols.intern_predicate("owns", 2);
+        // The same name with a different arity is listed separately
+        symbols.intern_predicate("owns", 1);
+        assert_eq!(
+            symbols.predicates().collect::<Vec<_>>(),
+            [("bird", 1), ("owns", 1), ("owns", 2)]
+        );
+        assert!(symbols.is_predicate("owns"));
+        assert!(!symbols.is_predicate("own"));
+        assert!(!symbols.is_predicate("ownsx"));
+        assert_eq!(symbols.name(str_to_ident("bird")), "bird");
+    }
+
+    #[test]
+    fn constants() {
+        let mut symbols = SymbolTable::default();
+        symbols.intern_constant("tweety");
+        symbols.intern_constant("mary");
+        symbols.intern_constant("tweety");
+        // Free variables are interned, but they aren't constants
+        symbols.intern("X");
+        assert_eq!(symbols.constants().collect::<Vec<_>>(), ["mary", "tweety"]);
+        assert_eq!(symbols.name(str_to_ident("X")), "X");
+        assert_eq!(symbols.predicates().count(), 0);
+    }
+}
@@ -0,0 +1,515 @@
+//! Checks the answers to questions in Leuchtkraft scripts against the values
+//! written in `// expect: X = false` comments.
+//!
+//! Multiple expectations are separated by commas (`// expect: X = true, Y = either`).
+//! Anything after the last value is ignored, so it can be used to explain the answer.
+
+use crate::debug::lint::LintLevels;
+use crate::diagnostics::{
+    Annotation, AnnotationType, Diagnostic, DisplayDiagnostic, Location, MessageFormat,
+};
+use crate::exit::ExitStatus;
+use crate::interpreter::Interpreter;
+use crate::logic::UnknownValue;
+use crate::parser::span::Span;
+use crate::repl::{run_file_with, Context, LineHandler};
+use crate::util::collect_scripts;
+use std::io;
+use std::path::{Path, PathBuf};
+use termcolor::{Color, ColorSpec, WriteColor};
+
+/// The value a single unknown is expected to resolve to
+struct Expectation {
+    unknown: String,
+    /// Span of the entire `name = value` pair
+    span: Span,
+    value: UnknownValue,
+}
+
+pub enum TestFailure {
+    /// The expectation comment could not be parsed
+    MalformedExpectation { span: Span },
+    /// The expectation is attached to a line that is not a question
+    NotAQuestion { span: Span },
+    /// The question does not contain the expected unknown
+    MissingUnknown { unknown: String, span: Span },
+    /// The unknown resolved to a different value than expected
+    Mismatch {
+        unknown: String,
+        expected: UnknownValue,
+        found: UnknownValue,
+        span: Span,
+    },
+}
+
+impl TestFailure {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TestFailure::MalformedExpectation { .. } => "E004",
+            TestFailure::NotAQuestion { .. } => "E005",
+            TestFailure::MissingUnknown { .. } => "E006",
+            TestFailure::Mismatch { .. } => "E007",
+        }
+    }
+}
+
+impl<'a> From<(TestFailure, &'a str)> for Diagnostic<'a> {
+    fn from(other: (TestFailure, &'a str)) -> Self {
+        let annotations = match &other.0 {
+            TestFailure::MalformedExpectation { span } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: "expected a list of `name = value` pairs".to_owned(),
+                location: None,
+            }],
+            TestFailure::NotAQuestion { span } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: "this line does not ask a question".to_owned(),
+                location: None,
+            }],
+            TestFailure::MissingUnknown { unknown, span } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: format!("{}? does not appear in the question", unknown),
+                location: None,
+            }],
+            TestFailure::Mismatch { expected, span, .. } => vec![Annotation {
+                annotation_type: AnnotationType::Info,
+                span: *span,
+                msg: format!("expected {:?}", expected),
+                location: None,
+            }],
+        };
+
+        let msg = match &other.0 {
+            TestFailure::MalformedExpectation { .. } => "Malformed expectation".to_owned(),
+            TestFailure::NotAQuestion { .. } => "Expectation without a question".to_owned(),
+            TestFailure::MissingUnknown { unknown, .. } => {
+                format!("Expected a value for unknown {:?}", unknown)
+            }
+            TestFailure::Mismatch {
+                unknown,
+                expected,
+                found,
+                ..
+            } => format!(
+                "{}? resolved to {:?}, but {:?} was expected",
+                unknown, found, expected
+            ),
+        };
+
+        let note = match &other.0 {
+            TestFailure::MalformedExpectation { .. } => {
+                Some("expectations look like '// expect: X = false, Y = either'")
+            }
+            TestFailure::NotAQuestion { .. } => None,
+            TestFailure::MissingUnknown { .. } => None,
+            TestFailure::Mismatch { .. } => None,
+        };
+
+        Self {
+            code: Some(other.0.code()),
+            buffer: other.1,
+            annotation_type: AnnotationType::Error,
+            annotations,
+            msg,
+            note,
+            suggestions: vec![],
+        }
+    }
+}
+
+fn parse_value(word: &str) -> Option<UnknownValue> {
+    match word.to_lowercase().as_str() {
+        "true" => Some(UnknownValue::True),
+        "false" => Some(UnknownValue::False),
+        "either" => Some(UnknownValue::Either),
+        "neither" => Some(UnknownValue::Neither),
+        _ => None,
+    }
+}
+
+/// Parse the expectations in a line, if it contains any
+fn parse_expectations(line: &str) -> Option<Result<Vec<Expectation>, TestFailure>> {
+    let comment_start = line.find("//")? + 2;
+    let comment = &line[comment_start..];
+    let offset = comment_start + comment.len() - comment.trim_start().len();
+    if !comment.trim_start().starts_with("expect:") {
+        return None;
+    }
+    let mut pos = offset + "expect:".len();
+
+    // The line is searched by byte, but spans are character positions
+    let span =
+        |start: usize, end: usize| Span(line[..start].chars().count(), line[..end].chars().count());
+    let malformed = TestFailure::MalformedExpectation {
+        span: span(offset, line.len()),
+    };
+
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let skip_whitespace = |pos: &mut usize| {
+        *pos += line[*pos..].len() - line[*pos..].trim_start().len();
+    };
+    let read_word = |pos: &mut usize| {
+        let start = *pos;
+        *pos += line[*pos..]
+            .find(|c: char| !is_ident_char(c))
+            .unwrap_or(line.len() - *pos);
+        Span(start, *pos)
+    };
+
+    let mut expectations = vec![];
+    loop {
+        skip_whitespace(&mut pos);
+        let unknown_span = read_word(&mut pos);
+        if unknown_span.0 == unknown_span.1 {
+            return Some(Err(malformed));
+        }
+        // The questionmark after the unknown is optional
+        if line[pos..].starts_with('?') {
+            pos += 1;
+        }
+
+        skip_whitespace(&mut pos);
+        if !line[pos..].starts_with('=') {
+            return Some(Err(malformed));
+        }
+        pos += 1;
+
+        skip_whitespace(&mut pos);
+        let value_span = read_word(&mut pos);
+        let value = match parse_value(&line[value_span.0..value_span.1]) {
+            Some(value) => value,
+            None => return Some(Err(malformed)),
+        };
+
+        expectations.push(Expectation {
+            unknown: line[unknown_span.0..unknown_span.1].to_owned(),
+            span: span(unknown_span.0, value_span.1),
+            value,
+        });
+
+        skip_whitespace(&mut pos);
+        if !line[pos..].starts_with(',') {
+            // Anything else is a free-form explanation
+            break;
+        }
+        pos += 1;
+    }
+    Some(Ok(expectations))
+}
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+}
+
+fn report<W: WriteColor>(writer: &mut W, location: &str, passed: bool) -> io::Result<()> {
+    write!(writer, "test {} ... ", location)?;
+    if passed {
+        writer.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
+        writeln!(writer, "ok")?;
+    } else {
+        writer.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+        writeln!(writer, "FAILED")?;
+    }
+    writer.reset()
+}
+
+/// Compares the answers to the questions in a script with their expectations
+struct Checker<'a, R> {
+    /// The script being tested, the files it imports are not checked
+    path: &'a Path,
+    report: &'a mut R,
+    summary: &'a mut Summary,
+    /// The first error while writing the report or the diagnostics
+    result: io::Result<()>,
+}
+
+impl<R: WriteColor> LineHandler for Checker<'_, R> {
+    fn executed<W: WriteColor>(
+        &mut self,
+        i: &mut Interpreter,
+        line: &str,
+        location: Location,
+        _: Option<String>,
+        writer: &mut W,
+        format: MessageFormat,
+    ) {
+        // Take the answer even if it is not checked, so that it can't be mistaken
+        // for the answer to a later line
+        let answer = i.take_answer();
+        let in_script = matches!(
+            i.sources().context(location.source),
+            Context::File(path) if path == self.path
+        );
+        if in_script && self.result.is_ok() {
+            self.result = self.check(i, line, location, answer, writer, format);
+        }
+    }
+}
+
+impl<R: WriteColor> Checker<'_, R> {
+    fn check<W: WriteColor>(
+        &mut self,
+        i: &Interpreter,
+        line: &str,
+        location: Location,
+        answer: Option<Vec<(String, UnknownValue)>>,
+        writer: &mut W,
+        format: MessageFormat,
+    ) -> io::Result<()> {
+        let position = format!("{}:{}", self.path.display(), location.line);
+        let expectations = match parse_expectations(line) {
+            Some(Ok(expectations)) => expectations,
+            Some(Err(failure)) => {
+                report(self.report, &position, false)?;
+                writer.emit(
+                    Diagnostic::from((failure, line)),
+                    location,
+                    i.sources(),
+                    format,
+                )?;
+                self.summary.failed += 1;
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
+        let answer = match answer {
+            Some(answer) => answer,
+            None => {
+                let failure = TestFailure::NotAQuestion {
+                    span: Span(
+                        0,
+                        line[..line.find("//").unwrap_or(line.len())]
+                            .trim_end()
+                            .chars()
+                            .count(),
+                    ),
+                };
+                report(self.report, &position, false)?;
+                writer.emit(
+                    Diagnostic::from((failure, line)),
+                    location,
+                    i.sources(),
+                    format,
+                )?;
+                self.summary.failed += 1;
+                return Ok(());
+            }
+        };
+
+        let mut failures = vec![];
+        for expectation in expectations {
+            match answer.iter().find(|(name, _)| name == &expectation.unknown) {
+                Some((_, found)) if found == &expectation.value => {}
+                Some((_, found)) => failures.push(TestFailure::Mismatch {
+                    unknown: expectation.unknown,
+                    expected: expectation.value,
+                    found: found.clone(),
+                    span: expectation.span,
+                }),
+                None => failures.push(TestFailure::MissingUnknown {
+                    unknown: expectation.unknown,
+                    span: expectation.span,
+                }),
+            }
+        }
+
+        report(self.report, &position, failures.is_empty())?;
+        if failures.is_empty() {
+            self.summary.passed += 1;
+        } else {
+            self.summary.failed += 1;
+        }
+        for failure in failures {
+            writer.emit(
+                Diagnostic::from((failure, line)),
+                location,
+                i.sources(),
+                format,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a script, checking the answers to its questions.
+/// The results are written to `writer`, diagnostics to `diagnostics`.
+fn test_script<R: WriteColor, D: WriteColor>(
+    path: &Path,
+    cli_levels: &LintLevels,
+    writer: &mut R,
+    diagnostics: &mut D,
+    summary: &mut Summary,
+    format: MessageFormat,
+) -> io::Result<()> {
+    // Warnings are denied just like when running the script
+    let lint_levels = match LintLevels::for_script(path, cli_levels) {
+        Ok(lint_levels) => lint_levels,
+        Err((config, msg)) => {
+            writeln!(diagnostics, "Could not load {}:\n{}", config.display(), msg)?;
+            summary.failed += 1;
+            return Ok(());
+        }
+    };
+    let mut i = Interpreter::new();
+    i.set_lint_levels(lint_levels);
+    let mut checker = Checker {
+        path,
+        report: writer,
+        summary,
+        result: Ok(()),
+    };
+    let status = run_file_with(&mut i, path, &mut checker, diagnostics, format);
+    checker.result?;
+
+    match status {
+        Ok(ExitStatus::Success | ExitStatus::Contradiction) => {}
+        // The rest of the script can't be trusted after an error
+        Ok(_) => {
+            report(writer, &path.display().to_string(), false)?;
+            summary.failed += 1;
+        }
+        Err(e) => {
+            writeln!(diagnostics, "Could not read {}:\n{:?}", path.display(), e)?;
+            summary.failed += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Run every question with an expectation in the given scripts (or directories
+/// containing scripts) and report the results to `writer`.
+/// The lint levels from the command line override those from the config files.
+/// Diagnostics are written to `diagnostics`.
+/// Returns whether or not all expectations were met.
+pub fn run_tests<W: WriteColor, D: WriteColor>(
+    paths: &[PathBuf],
+    cli_levels: &LintLevels,
+    writer: &mut W,
+    diagnostics: &mut D,
+    format: MessageFormat,
+) -> io::Result<bool> {
+    let mut scripts = vec![];
+    for path in paths {
+        collect_scripts(path, &mut scripts)?;
+    }
+
+    let mut summary = Summary::default();
+    for script in &scripts {
+        test_script(
+            script,
+            cli_levels,
+            writer,
+            diagnostics,
+            &mut summary,
+            format,
+        )?;
+    }
+
+    write!(writer, "\ntest result: ")?;
+    if summary.failed == 0 {
+        writer.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
+        write!(writer, "ok")?;
+    } else {
+        writer.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
+        write!(writer, "FAILED")?;
+    }
+    writer.reset()?;
+    writeln!(
+        writer,
+        ". {} passed; {} failed",
+        summary.passed, summary.failed
+    )?;
+    Ok(summary.failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::lint::Level;
+    use std::fs;
+    use termcolor::Buffer;
+
+    #[test]
+    fn expectations() {
+        let expectations = parse_expectations("X? => a(x) => X? // expect: X = true, Y? = either")
+            .unwrap()
+            .ok()
+            .unwrap();
+        assert_eq!(expectations.len(), 2);
+        assert_eq!(expectations[0].unknown, "X");
+        assert_eq!(expectations[0].value, UnknownValue::True);
+        assert_eq!(expectations[1].unknown, "Y");
+        assert_eq!(expectations[1].value, UnknownValue::Either);
+
+        assert!(parse_expectations("true => a(x) // a comment").is_none());
+        assert!(matches!(
+            parse_expectations("X? => a(x) => X? // expect: X = maybe"),
+            Some(Err(TestFailure::MalformedExpectation { .. }))
+        ));
+    }
+
+    #[test]
+    fn expectation_spans_count_characters() {
+        let line = "X? => größe(x) => X? // expect: X = true";
+        let expectations = parse_expectations(line).unwrap().ok().unwrap();
+        let span = expectations[0].span;
+        assert_eq!(span.1, line.chars().count());
+        assert_eq!(span.0, span.1 - "X = true".len());
+    }
+
+    #[test]
+    fn diagnostics_are_separate_from_the_report() {
+        let path = std::env::temp_dir().join("leuchtkraft_diagnostics_are_separate.le");
+        fs::write(
+            &path,
+            "true => a(x)\nX? => a(x) => X? // expect: X = false\n",
+        )
+        .unwrap();
+
+        let mut writer = Buffer::no_color();
+        let mut diagnostics = Buffer::no_color();
+        let passed = run_tests(
+            std::slice::from_ref(&path),
+            &LintLevels::default(),
+            &mut writer,
+            &mut diagnostics,
+            MessageFormat::Human,
+        )
+        .unwrap();
+        let report = String::from_utf8(writer.into_inner()).unwrap();
+        let diagnostics = String::from_utf8(diagnostics.into_inner()).unwrap();
+
+        assert!(!passed);
+        assert!(report.contains(&format!("test {}:2 ... FAILED", path.display())));
+        assert!(!report.contains("E007"));
+        assert!(diagnostics.contains("error[E007]"));
+    }
+
+    #[test]
+    fn denied_warnings_fail() {
+        let path = std::env::temp_dir().join("leuchtkraft_denied_warnings_fail.le");
+        fs::write(
+            &path,
+            "true => a(x) and true\nX? => a(x) => X? // expect: X = true\n",
+        )
+        .unwrap();
+
+        let mut cli_levels = LintLevels::default();
+        cli_levels.set("redundant_true", Level::Deny);
+        let passed = run_tests(
+            std::slice::from_ref(&path),
+            &cli_levels,
+            &mut Buffer::no_color(),
+            &mut Buffer::no_color(),
+            MessageFormat::Human,
+        )
+        .unwrap();
+        assert!(!passed);
+    }
+}
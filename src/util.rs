@@ -1,6 +1,9 @@
 // use crate::debug::diagnostic::Diagnostic;
 use std::collections::hash_map::DefaultHasher;
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Internally, u64s are used since they can be compared much faster than strings
 pub fn calculate_hash<T: Hash>(t: &T) -> u64 {
@@ -8,3 +11,96 @@ pub fn calculate_hash<T: Hash>(t: &T) -> u64 {
     t.hash(&mut s);
     s.finish()
 }
+
+/// Collect all Leuchtkraft scripts in a directory (or the path itself, if it is a file)
+pub fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "le") {
+                collect_scripts(&entry, scripts)?;
+            }
+        }
+    } else {
+        scripts.push(path.to_owned());
+    }
+    Ok(())
+}
+
+/// The number of single-character insertions, deletions and substitutions
+/// needed to turn one string into another (Levenshtein distance)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + usize::from(a_char != *b_char);
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(distances[j + 1] + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Find the candidate that is most similar to a name, for "did you mean" suggestions.
+/// Candidates that are too different from the name (or equal to it) are never suggested.
+pub fn closest_match<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    // Short names are only similar to names that differ in case
+    let max_distance = name.chars().count() / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| {
+            let distance = if candidate.eq_ignore_ascii_case(name) {
+                0
+            } else {
+                edit_distance(name, candidate)
+            };
+            (distance, candidate)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, candidate)| (*distance, *candidate))
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "bird"), 4);
+        assert_eq!(edit_distance("bird", ""), 4);
+        assert_eq!(edit_distance("canfly", "cnafly"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        // Characters are compared, not bytes
+        assert_eq!(edit_distance("größe", "grösse"), 2);
+        assert_eq!(edit_distance("ä", "a"), 1);
+    }
+
+    #[test]
+    fn closest_matches() {
+        let candidates = ["canfly", "bird", "healthy"];
+        assert_eq!(closest_match("cnafly", candidates), Some("canfly"));
+        assert_eq!(closest_match("brd", candidates), Some("bird"));
+        // Short names allow fewer differences
+        assert_eq!(closest_match("bd", candidates), None);
+        assert_eq!(closest_match("Bird", candidates), Some("bird"));
+        // Names are never suggested as their own replacement
+        assert_eq!(closest_match("bird", candidates), None);
+        assert_eq!(closest_match("", candidates), None);
+        assert_eq!(closest_match("bird", []), None);
+        // Ties are broken alphabetically
+        assert_eq!(closest_match("owned", ["owner", "owned1"]), Some("owned1"));
+        assert_eq!(closest_match("größe", ["grösse", "große"]), Some("große"));
+    }
+}